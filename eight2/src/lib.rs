@@ -0,0 +1,54 @@
+pub struct SpaceImage {
+    layers: Vec<Vec<u32>>,
+    width: usize,
+    height: usize,
+}
+
+impl SpaceImage {
+    pub fn new(input: &str, width: usize, height: usize) -> Self {
+        let pixels_per_layer = width * height;
+        let mut layers = Vec::with_capacity(input.len() / pixels_per_layer);
+        let mut layer = Vec::with_capacity(pixels_per_layer);
+        for (i, pixel_str) in input.chars().enumerate() {
+            let pixel = pixel_str.to_digit(10).expect("Non-digit input");
+            layer.push(pixel);
+            if (i + 1) % pixels_per_layer == 0 {
+                layers.push(layer);
+                layer = Vec::with_capacity(pixels_per_layer);
+            }
+        }
+        Self {
+            layers,
+            width,
+            height,
+        }
+    }
+
+    /// Pixels seen through all the layers, as rows from the top. Transparent pixels (2) are only
+    /// left where every layer is transparent.
+    pub fn decode(&self) -> Vec<Vec<u32>> {
+        let mut decoded_layer = Vec::with_capacity(self.width * self.height);
+        decoded_layer.resize(self.width * self.height, 2);
+        for layer in self.layers.iter().rev() {
+            for (px_index, &px) in layer.iter().enumerate() {
+                if px != 2 {
+                    decoded_layer[px_index] = px;
+                }
+            }
+        }
+        decoded_layer.chunks(self.width).map(<[u32]>::to_vec).collect()
+    }
+}
+
+/// Pixels as lines of block characters: 0 (black) is shaded, 1 (white) is solid and anything else
+/// (transparent) is left blank
+pub fn render(rows: &[Vec<u32>]) -> String {
+    rows.iter().map(|row| row.iter().map(
+        |&px| {
+            match px {
+                0 => "▒",
+                1 => "█",
+                _ => " ",
+            }
+        }).collect::<Vec<&str>>().join("")).collect::<Vec<_>>().join("\n")
+}
@@ -0,0 +1,18 @@
+use eight2::{render, SpaceImage};
+
+#[test]
+fn decodes_one_row_per_line_of_the_image() {
+//    Example from day 8 part 2
+    let image = SpaceImage::new("0222112222120000", 2, 2);
+    let decoded = image.decode();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded, vec![vec![0, 1], vec![1, 0]]);
+    assert_eq!(render(&decoded), "▒█\n█▒");
+}
+
+#[test]
+fn transparent_where_every_layer_is() {
+    let image = SpaceImage::new("221202", 3, 1);
+    assert_eq!(image.decode(), vec![vec![2, 0, 1]]);
+    assert_eq!(render(&image.decode()), " ▒█");
+}
@@ -0,0 +1,36 @@
+//! Translates every program in programs/ with `nine1::aot`. Files ending in .src are compiled
+//! with `nine1::compiler` first.
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=programs");
+    let mut paths = fs::read_dir("programs").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut code = String::new();
+    let mut table = String::new();
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        let intcode = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("src") => nine1::compiler::compile(&source)
+                .unwrap_or_else(|err| panic!("Failed to compile {}: {}", path.display(), err)),
+            _ => source.trim().to_owned(),
+        };
+        let memory = nine1::loader::parse(&intcode)
+            .unwrap_or_else(|err| panic!("Failed to load {}: {}", path.display(), err));
+        code.push_str(&nine1::aot::translate(name, &memory));
+        writeln!(code, "fn run_{}(inputs: &[nine1::Integer]) -> Outcome {{ run_translated!({}, inputs) }}", name, name).unwrap();
+        writeln!(table, "    Conformance {{ name: {:?}, intcode: {:?}, run: run_{} }},", name, intcode, name).unwrap();
+    }
+    writeln!(code, "pub const PROGRAMS: &[Conformance] = &[\n{}];", table).unwrap();
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("programs.rs");
+    fs::write(out, code).unwrap();
+}
@@ -0,0 +1,39 @@
+//! Programs from programs/, translated to Rust by the build script, for checking that
+//! translations behave exactly like the interpreter.
+use std::cell::RefCell;
+
+use nine1::{IntcodeError, Integer, Program};
+
+/// Result of a run along with everything it output
+pub type Outcome = (Result<Option<Integer>, IntcodeError>, Vec<Integer>);
+
+pub struct Conformance {
+    pub name: &'static str,
+    pub intcode: &'static str,
+    /// Run the translation with the given inputs
+    pub run: fn(&[Integer]) -> Outcome,
+}
+
+macro_rules! run_translated {
+    ($function:ident, $inputs:expr) => {{
+        let inputs = RefCell::new($inputs.iter().cloned());
+        let outputs = RefCell::new(Vec::new());
+        let result = $function(|| inputs.borrow_mut().next(), |val| outputs.borrow_mut().push(val));
+        (result, outputs.into_inner())
+    }};
+}
+
+include!(concat!(env!("OUT_DIR"), "/programs.rs"));
+
+pub fn program(name: &str) -> &'static Conformance {
+    PROGRAMS.iter().find(|program| program.name == name)
+        .unwrap_or_else(|| panic!("No program called {}", name))
+}
+
+/// Run the program in the interpreter with the given inputs
+pub fn interpret(intcode: &str, inputs: &[Integer]) -> Outcome {
+    let inputs = RefCell::new(inputs.iter().cloned());
+    let outputs = RefCell::new(Vec::new());
+    let result = Program::new(intcode, || inputs.borrow_mut().next(), |val| outputs.borrow_mut().push(val)).execute().result();
+    (result, outputs.into_inner())
+}
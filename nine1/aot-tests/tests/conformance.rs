@@ -0,0 +1,67 @@
+use aot_tests::{interpret, program, PROGRAMS};
+use nine1::{IntcodeError, Integer};
+
+/// Inputs to run each program with, on top of running it with no input at all
+const INPUTS: &[(&str, &[&[Integer]])] = &[
+    ("boost", &[&[1], &[2]]),
+    ("day5_compare_to_8", &[&[7], &[8], &[9]]),
+    ("day5_jumps", &[&[0], &[5]]),
+    ("fib", &[&[-1], &[0, 1, 2, 10, -1], &[20, -1], &[5]]),
+];
+
+fn assert_conforms(name: &str, inputs: &[Integer]) {
+    let program = program(name);
+    let translated = (program.run)(inputs);
+    let interpreted = interpret(program.intcode, inputs);
+    assert_eq!(translated, interpreted, "{} with inputs {:?}", name, inputs);
+}
+
+#[test]
+fn translations_match_interpreter() {
+    for program in PROGRAMS {
+        assert_conforms(program.name, &[]);
+        let inputs = INPUTS.iter().find(|(name, _)| *name == program.name).map_or(&[][..], |(_, inputs)| inputs);
+        for inputs in inputs.iter() {
+            assert_conforms(program.name, inputs);
+        }
+    }
+}
+
+#[test]
+fn boost() {
+    assert_eq!((program("boost").run)(&[2]), (Ok(Some(70634)), vec![70634]));
+}
+
+#[test]
+fn compiled_program() {
+    assert_eq!((program("fib").run)(&[0, 1, 10, 20, -1]), (Ok(Some(6765)), vec![0, 1, 55, 6765]));
+}
+
+#[test]
+fn day9() {
+    let quine = program("day9_quine");
+    let expected = quine.intcode.split(',').map(|x| x.parse().unwrap()).collect::<Vec<Integer>>();
+    assert_eq!((quine.run)(&[]).1, expected);
+    assert_eq!((program("day9_large_number").run)(&[]).1, vec![1125899906842624]);
+}
+
+#[test]
+fn self_modifying_programs_fall_back_to_interpreter() {
+//    Overwrites the param of the following Output instruction
+    assert_eq!((program("self_modifying").run)(&[]), (Ok(Some(42)), vec![42]));
+//    Overwrites data, which then gets executed
+    assert_eq!((program("day5_param_modes").run)(&[]), (Ok(None), vec![]));
+    assert_eq!((program("day2_example").run)(&[]), (Ok(None), vec![]));
+}
+
+#[test]
+fn errors_match_interpreter() {
+    assert_eq!((program("negative_address").run)(&[]),
+               (Err(IntcodeError::NegativeAddress { pos: 3, address: -1 }), vec![1]));
+    assert_eq!((program("immediate_write").run)(&[]),
+               (Err(IntcodeError::ImmediateWrite { pos: 5 }), vec![1]));
+    assert_eq!((program("unknown_opcode").run)(&[]),
+               (Err(IntcodeError::UnknownOpcode { pos: 2, opcode: 77 }), vec![1]));
+    assert_eq!((program("day5_compare_to_8").run)(&[]),
+               (Err(IntcodeError::InputExhausted { pos: 0 }), vec![]));
+}
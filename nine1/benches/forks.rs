@@ -0,0 +1,43 @@
+//! Cost of forking machines, which search harnesses like the droid do once per state they visit.
+//! Run with `cargo bench --bench forks`; each fork should take microseconds, i.e. many thousands
+//! of forks a second.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use nine1::{droid, loader, Machine, State};
+
+fn boost() -> Machine {
+    let mut machine = Machine::new(loader::parse(include_str!("../aot-tests/programs/boost.txt")).unwrap());
+    assert_eq!(machine.resume(), Ok(State::NeedsInput));
+    machine
+}
+
+/// Corridor of the given length heading east, with the target at the end of it
+fn corridor(length: usize) -> String {
+    nine1::compiler::compile(&format!("
+        let x = 0;
+        while 1 {{
+            if input() == 4 && x < {} {{
+                x = x + 1;
+                output(1 + (x == {}));
+            }} else {{
+                output(0);
+            }}
+        }}
+    ", length, length)).unwrap()
+}
+
+fn forks(c: &mut Criterion) {
+    let machine = boost();
+    c.bench_function("fork", |b| b.iter(|| black_box(machine.clone())));
+    c.bench_function("fork and write", |b| b.iter(|| {
+        let mut fork = machine.clone();
+        fork.push_input(1);
+        black_box(fork.resume().unwrap())
+    }));
+
+    let intcode = corridor(100);
+    c.bench_function("explore 100 cells", |b| b.iter(|| droid::explore(&intcode).unwrap()));
+}
+
+criterion_group!(benches, forks);
+criterion_main!(benches);
@@ -0,0 +1,309 @@
+//! Ahead-of-time translation of intcode programs to Rust, for programs which run long enough that
+//! decoding every instruction in the interpreter starts to hurt.
+//!
+//! `translate` turns a program into the source of a function which behaves like
+//! `Program::execute`, and is meant to be called from a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::{env, fs, path::Path};
+//!
+//! let intcode = fs::read_to_string("boost.txt").unwrap();
+//! let memory: Vec<_> = intcode.trim().split(',').map(|x| x.parse().unwrap()).collect();
+//! let out = Path::new(&env::var("OUT_DIR").unwrap()).join("boost.rs");
+//! fs::write(out, nine1::aot::translate("boost", &memory)).unwrap();
+//! ```
+//!
+//! ```ignore
+//! // src/lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/boost.rs"));
+//!
+//! let answer = boost(|| Some(2), |val| println!("{}", val))?;
+//! ```
+//!
+//! The program is split into basic blocks, which start at address 0, the targets of jumps and
+//! anything else that looks like a code address (such as return addresses pushed by a call).
+//! Each block becomes straight-line Rust code. Jumping anywhere else, or writing to memory which
+//! holds a translated instruction, hands the rest of the run over to the interpreter, so
+//! self-modifying programs still behave exactly as they would in `Program`.
+//!
+//! Translated programs don't support extensions, devices, coverage, recording or cycle limits.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::disasm::{decode_builtins, Decoded};
+use crate::{Instruction, Integer, IntcodeError, ParamMode, Position, Program, MEMORY_SIZE};
+
+/// State of a translated program while it runs. Only meant to be used by code from `translate`.
+pub struct Runtime<I, O>
+    where I: Fn() -> Option<Integer>, O: Fn(Integer)
+{
+    memory: Vec<Integer>,
+    relative_base: Integer,
+    /// Whether each address of the original program holds part of a translated instruction
+    code: Vec<bool>,
+    input_fn: I,
+    output_fn: O,
+    prev_output: Option<Integer>,
+}
+
+impl<I, O> Runtime<I, O>
+    where I: Fn() -> Option<Integer>, O: Fn(Integer)
+{
+    /// `code` gives the ranges of addresses, end exclusive, which were translated
+    pub fn new(memory: &[Integer], code: &[(Position, Position)], input_fn: I, output_fn: O) -> Self {
+        let mut is_code = vec![false; memory.len()];
+        for &(start, end) in code {
+            for flag in &mut is_code[start as usize..end as usize] {
+                *flag = true;
+            }
+        }
+        let mut memory = memory.to_vec();
+        memory.resize(MEMORY_SIZE, 0);
+        Self {
+            memory,
+            relative_base: 0,
+            code: is_code,
+            input_fn,
+            output_fn,
+            prev_output: None,
+        }
+    }
+
+    #[inline]
+    pub fn address(&self, pos: Position, address: Integer) -> Result<Position, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress { pos, address });
+        }
+        if address as usize >= MEMORY_SIZE {
+            return Err(IntcodeError::AddressOutOfRange { pos, address });
+        }
+        Ok(address as Position)
+    }
+
+    #[inline]
+    pub fn relative_address(&self, pos: Position, offset: Integer) -> Result<Position, IntcodeError> {
+        let address = self.relative_base.checked_add(offset).ok_or(IntcodeError::Overflow { pos })?;
+        self.address(pos, address)
+    }
+
+    /// Sum for the Add instruction at pos
+    #[inline]
+    pub fn add(&self, pos: Position, a: Integer, b: Integer) -> Result<Integer, IntcodeError> {
+        a.checked_add(b).ok_or(IntcodeError::Overflow { pos })
+    }
+
+    /// Product for the Multiply instruction at pos
+    #[inline]
+    pub fn multiply(&self, pos: Position, a: Integer, b: Integer) -> Result<Integer, IntcodeError> {
+        a.checked_mul(b).ok_or(IntcodeError::Overflow { pos })
+    }
+
+    /// Destination of an output param in immediate mode, which is always an error
+    #[inline]
+    pub fn immediate_address(&self, pos: Position) -> Result<Position, IntcodeError> {
+        Err(IntcodeError::ImmediateWrite { pos })
+    }
+
+    /// Read the param at pos in position mode
+    #[inline]
+    pub fn load(&self, pos: Position, address: Integer) -> Result<Integer, IntcodeError> {
+        Ok(self.memory[self.address(pos, address)? as usize])
+    }
+
+    /// Read the param at pos in relative mode
+    #[inline]
+    pub fn load_relative(&self, pos: Position, offset: Integer) -> Result<Integer, IntcodeError> {
+        Ok(self.memory[self.relative_address(pos, offset)? as usize])
+    }
+
+    /// Write to memory, returning true if that changed translated code, in which case the
+    /// translation can't be trusted from here on
+    #[inline]
+    pub fn store(&mut self, address: Position, val: Integer) -> bool {
+        self.memory[address as usize] = val;
+        self.code.get(address as usize).cloned().unwrap_or(false)
+    }
+
+    /// Move the relative base for the RelativeBaseOffset instruction at pos
+    #[inline]
+    pub fn adjust_relative_base(&mut self, pos: Position, offset: Integer) -> Result<(), IntcodeError> {
+        self.relative_base = self.relative_base.checked_add(offset).ok_or(IntcodeError::Overflow { pos })?;
+        Ok(())
+    }
+
+    /// Input for the Input instruction at pos
+    pub fn input(&mut self, pos: Position) -> Result<Integer, IntcodeError> {
+        (self.input_fn)().ok_or(IntcodeError::InputExhausted { pos })
+    }
+
+    pub fn output(&mut self, val: Integer) {
+        (self.output_fn)(val);
+        self.prev_output = Some(val);
+    }
+
+    /// Result of halting, which is the last output like `Program::execute`
+    pub fn finish(self) -> Result<Option<Integer>, IntcodeError> {
+        Ok(self.prev_output)
+    }
+
+    /// Continue the run in the interpreter, starting at pos
+    pub fn interpret(self, pos: Position) -> Result<Option<Integer>, IntcodeError> {
+        log::debug!("Falling back to the interpreter at {}", pos);
+        let prev_output = self.prev_output;
+        let mut program = Program::from_memory(self.memory, self.input_fn, self.output_fn);
+        Ok(program.run_from(pos, self.relative_base)?.or(prev_output))
+    }
+}
+
+impl Decoded {
+    fn is_jump(&self) -> bool {
+        matches!(self.instruction, Instruction::JumpIfTrue | Instruction::JumpIfFalse | Instruction::Halt)
+    }
+
+    /// Expression reading param i
+    fn read(&self, i: usize) -> String {
+        let pos = self.pos() + 1 + i as Position;
+        match self.params[i] {
+            (ParamMode::POSITION, val) => format!("rt.load({}, {})?", pos, val),
+            (ParamMode::IMMEDIATE, val) => format!("({}i64)", val),
+            (ParamMode::RELATIVE, val) => format!("rt.load_relative({}, {})?", pos, val),
+        }
+    }
+
+    /// Expression giving the address param i writes to
+    fn dest(&self, i: usize) -> String {
+        let pos = self.pos() + 1 + i as Position;
+        match self.params[i] {
+            (ParamMode::POSITION, val) => format!("rt.address({}, {})?", pos, val),
+            (ParamMode::IMMEDIATE, _) => format!("rt.immediate_address({})?", pos),
+            (ParamMode::RELATIVE, val) => format!("rt.relative_address({}, {})?", pos, val),
+        }
+    }
+}
+
+/// Addresses where a basic block starts
+fn leaders(decoded: &[Decoded]) -> BTreeSet<Position> {
+    let addresses: BTreeSet<Position> = decoded.iter().map(|d| d.pos()).collect();
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for d in decoded {
+        if d.is_jump() {
+            leaders.insert(d.next());
+        }
+//        Jump targets and values like return addresses which will probably be jumped to later
+        for &(mode, val) in d.params.iter() {
+            if mode == ParamMode::IMMEDIATE && val >= 0 {
+                leaders.insert(val as Position);
+            }
+        }
+    }
+    leaders.intersection(&addresses).cloned().collect()
+}
+
+/// Rust statements for one instruction. Blocks end with a jump, so those statements leave the
+/// loop iteration or the function.
+fn translate_instruction(code: &mut String, d: &Decoded) {
+    let pos = d.pos();
+    let next = d.next();
+    let store = |code: &mut String, dest: &str, val: &str| {
+        writeln!(code, "                if rt.store({}, {}) {{ return rt.interpret({}); }}", dest, val, next).unwrap();
+    };
+    writeln!(code, "                // {}", d.line.to_string().trim_start()).unwrap();
+    use Instruction::*;
+    match d.instruction {
+        Add | Multiply | LessThan | Equals => {
+            writeln!(code, "                let a = {};", d.read(0)).unwrap();
+            writeln!(code, "                let b = {};", d.read(1)).unwrap();
+            writeln!(code, "                let c = {};", d.dest(2)).unwrap();
+            let val = match d.instruction {
+                Add => format!("rt.add({}, a, b)?", pos),
+                Multiply => format!("rt.multiply({}, a, b)?", pos),
+                LessThan => "(a < b) as i64".to_owned(),
+                _ => "(a == b) as i64".to_owned(),
+            };
+            store(code, "c", &val);
+        }
+        Input => {
+            writeln!(code, "                let c = {};", d.dest(0)).unwrap();
+            writeln!(code, "                let a = rt.input({})?;", pos).unwrap();
+            store(code, "c", "a");
+        }
+        Output => {
+            writeln!(code, "                rt.output({});", d.read(0)).unwrap();
+        }
+        JumpIfTrue | JumpIfFalse => {
+            writeln!(code, "                let a = {};", d.read(0)).unwrap();
+            writeln!(code, "                let b = {};", d.read(1)).unwrap();
+            let op = if d.instruction == JumpIfTrue { "!=" } else { "==" };
+            writeln!(code, "                if a {} 0 {{", op).unwrap();
+            writeln!(code, "                    pc = rt.address({}, b)?;", pos).unwrap();
+            writeln!(code, "                    continue;").unwrap();
+            writeln!(code, "                }}").unwrap();
+        }
+        RelativeBaseOffset => {
+            writeln!(code, "                rt.adjust_relative_base({}, {})?;", pos, d.read(0)).unwrap();
+        }
+        Halt => {
+            writeln!(code, "                return rt.finish();").unwrap();
+        }
+    }
+}
+
+/// Rust source for a function called `name` which runs the program like `Program::execute`,
+/// taking the same input and output functions and giving the same result
+pub fn translate(name: &str, memory: &[Integer]) -> String {
+    let decoded = decode_builtins(memory);
+    let leaders = leaders(&decoded);
+
+    let mut code_ranges: Vec<(Position, Position)> = Vec::new();
+    for d in decoded.iter() {
+        match code_ranges.last_mut() {
+            Some(range) if range.1 == d.pos() => range.1 = d.next(),
+            _ => code_ranges.push((d.pos(), d.next())),
+        }
+    }
+
+    let mut code = String::new();
+    writeln!(code, "/// Translated from intcode by `nine1::aot`").unwrap();
+    writeln!(code, "#[allow(unused_mut, unused_parens, clippy::all)]").unwrap();
+    writeln!(code, "pub fn {}<I, O>(input_fn: I, output_fn: O) -> Result<Option<nine1::Integer>, nine1::IntcodeError>", name).unwrap();
+    writeln!(code, "    where I: Fn() -> Option<nine1::Integer>, O: Fn(nine1::Integer)").unwrap();
+    writeln!(code, "{{").unwrap();
+    writeln!(code, "    const MEMORY: &[nine1::Integer] = &{:?};", memory).unwrap();
+    writeln!(code, "    const CODE: &[(nine1::Position, nine1::Position)] = &{:?};", code_ranges).unwrap();
+    writeln!(code, "    let mut rt = nine1::aot::Runtime::new(MEMORY, CODE, input_fn, output_fn);").unwrap();
+    writeln!(code, "    let mut pc: nine1::Position = 0;").unwrap();
+    writeln!(code, "    loop {{").unwrap();
+    writeln!(code, "        match pc {{").unwrap();
+    let mut in_block = false;
+    for (i, d) in decoded.iter().enumerate() {
+        if leaders.contains(&d.pos()) {
+            if in_block {
+                writeln!(code, "                pc = {};", d.pos()).unwrap();
+                writeln!(code, "            }}").unwrap();
+            }
+            writeln!(code, "            {} => {{", d.pos()).unwrap();
+            in_block = true;
+        }
+        if !in_block {
+            continue;
+        }
+        translate_instruction(&mut code, d);
+//        Blocks also end at a gap in the code, e.g. where data is stored between functions
+        let falls_through = decoded.get(i + 1).is_some_and(|following| following.pos() == d.next());
+        if d.instruction == Instruction::Halt {
+            writeln!(code, "            }}").unwrap();
+            in_block = false;
+        } else if !falls_through {
+            writeln!(code, "                pc = {};", d.next()).unwrap();
+            writeln!(code, "            }}").unwrap();
+            in_block = false;
+        }
+    }
+    writeln!(code, "            _ => return rt.interpret(pc),").unwrap();
+    writeln!(code, "        }}").unwrap();
+    writeln!(code, "    }}").unwrap();
+    writeln!(code, "}}").unwrap();
+    code
+}
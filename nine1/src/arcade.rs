@@ -0,0 +1,237 @@
+//! Arcade cabinet for programs which draw a game by outputting `x, y, tile` triples, where the
+//! special position `(-1, 0)` sets the score instead of a tile. Whenever the program reads input
+//! it gets the joystick position from a `JoystickPolicy`, which can be a person at the terminal
+//! or an automated player such as `FollowBall`.
+//!
+//! ```
+//! use nine1::arcade::{self, FollowBall, Tile};
+//!
+//! // A wall, a block and a score of 12
+//! let intcode = "104,0,104,0,104,1,104,1,104,0,104,2,104,-1,104,0,104,12,99";
+//! let screen = arcade::run_headless(intcode, FollowBall).unwrap();
+//! assert_eq!((screen.count(Tile::Wall), screen.count(Tile::Block), screen.score()), (1, 1, 12));
+//! ```
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use num_enum::TryFromPrimitive;
+
+use crate::loader::{self, LoadError};
+use crate::{Integer, IntcodeError, Program};
+
+#[derive(Debug)]
+pub enum ArcadeError {
+    /// The game's intcode couldn't be parsed
+    Load(LoadError),
+    Intcode(IntcodeError),
+}
+
+impl fmt::Display for ArcadeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArcadeError::Load(err) => write!(f, "{}", err),
+            ArcadeError::Intcode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ArcadeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArcadeError::Load(err) => Some(err),
+            ArcadeError::Intcode(err) => Some(err),
+        }
+    }
+}
+
+impl From<LoadError> for ArcadeError {
+    fn from(err: LoadError) -> Self {
+        ArcadeError::Load(err)
+    }
+}
+
+impl From<IntcodeError> for ArcadeError {
+    fn from(err: IntcodeError) -> Self {
+        ArcadeError::Intcode(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum Tile {
+    Empty = 0,
+    Wall = 1,
+    Block = 2,
+    Paddle = 3,
+    Ball = 4,
+}
+
+impl Tile {
+    fn symbol(self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '█',
+            Tile::Block => '▒',
+            Tile::Paddle => '▔',
+            Tile::Ball => '●',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Joystick {
+    Left = -1,
+    Neutral = 0,
+    Right = 1,
+}
+
+/// Decides where to tilt the joystick each time the game reads input
+pub trait JoystickPolicy {
+    fn tilt(&mut self, screen: &Screen) -> Joystick;
+}
+
+impl<F> JoystickPolicy for F
+    where F: FnMut(&Screen) -> Joystick
+{
+    fn tilt(&mut self, screen: &Screen) -> Joystick {
+        self(screen)
+    }
+}
+
+/// Automated player which keeps the paddle under the ball
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FollowBall;
+
+impl JoystickPolicy for FollowBall {
+    fn tilt(&mut self, screen: &Screen) -> Joystick {
+        match (screen.ball(), screen.paddle()) {
+            (Some((ball, _)), Some((paddle, _))) if ball < paddle => Joystick::Left,
+            (Some((ball, _)), Some((paddle, _))) if ball > paddle => Joystick::Right,
+            _ => Joystick::Neutral,
+        }
+    }
+}
+
+/// What the game has drawn so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Screen {
+    tiles: BTreeMap<(Integer, Integer), Tile>,
+    score: Integer,
+    /// Values of a triple which hasn't been completed yet
+    pending: Vec<Integer>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an output value, drawing a tile or setting the score once a triple is complete
+    pub fn push(&mut self, val: Integer) {
+        self.pending.push(val);
+        if self.pending.len() < 3 {
+            return;
+        }
+        let (x, y, id) = (self.pending[0], self.pending[1], self.pending[2]);
+        self.pending.clear();
+        if (x, y) == (-1, 0) {
+            self.score = id;
+            return;
+        }
+        match u8::try_from(id).ok().and_then(|id| Tile::try_from(id).ok()) {
+            Some(tile) => {
+                self.tiles.insert((x, y), tile);
+            }
+            None => log::warn!("Ignoring unknown tile {} at {},{}", id, x, y),
+        }
+    }
+
+    pub fn tile(&self, x: Integer, y: Integer) -> Tile {
+        self.tiles.get(&(x, y)).cloned().unwrap_or(Tile::Empty)
+    }
+
+    pub fn score(&self) -> Integer {
+        self.score
+    }
+
+    /// Number of positions currently showing the tile, out of those that have been drawn
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.values().filter(|&&t| t == tile).count()
+    }
+
+    /// Position of the first tile of the kind, going by rows
+    fn find(&self, tile: Tile) -> Option<(Integer, Integer)> {
+        self.tiles.iter()
+            .filter(|(_, &t)| t == tile)
+            .map(|(&(x, y), _)| (y, x))
+            .min()
+            .map(|(y, x)| (x, y))
+    }
+
+    pub fn ball(&self) -> Option<(Integer, Integer)> {
+        self.find(Tile::Ball)
+    }
+
+    pub fn paddle(&self) -> Option<(Integer, Integer)> {
+        self.find(Tile::Paddle)
+    }
+
+    /// Score line followed by the tiles, from the top left of everything drawn so far
+    pub fn render(&self) -> String {
+        let mut rendered = format!("Score: {}\n", self.score);
+        if self.tiles.is_empty() {
+            return rendered;
+        }
+        let min_x = self.tiles.keys().map(|&(x, _)| x).min().unwrap();
+        let max_x = self.tiles.keys().map(|&(x, _)| x).max().unwrap();
+        let min_y = self.tiles.keys().map(|&(_, y)| y).min().unwrap();
+        let max_y = self.tiles.keys().map(|&(_, y)| y).max().unwrap();
+        let rows = (min_y..=max_y)
+            .map(|y| (min_x..=max_x).map(|x| self.tile(x, y).symbol()).collect::<String>())
+            .collect::<Vec<_>>();
+        rendered.push_str(&rows.join("\n"));
+        rendered
+    }
+}
+
+/// Program with 2 quarters put in, which games take as free play
+pub fn free_play(intcode: &str) -> Result<String, LoadError> {
+    let mut memory = loader::parse(intcode)?;
+    memory[0] = 2;
+    Ok(loader::to_text(&memory))
+}
+
+/// Run a game, asking the policy for the joystick position whenever it reads input.
+/// `on_frame` is shown the screen before each input and once more when the game halts.
+pub fn run<P, F>(intcode: &str, policy: P, on_frame: F) -> Result<Screen, ArcadeError>
+    where P: JoystickPolicy, F: FnMut(&Screen)
+{
+    let memory = loader::parse(intcode)?;
+    let screen = RefCell::new(Screen::new());
+    let policy = RefCell::new(policy);
+    let on_frame = RefCell::new(on_frame);
+    let mut program = Program::from_memory(
+        memory,
+        || {
+            let screen = screen.borrow();
+            (on_frame.borrow_mut())(&screen);
+            Some(policy.borrow_mut().tilt(&screen) as Integer)
+        },
+        |val| screen.borrow_mut().push(val));
+    program.execute().result()?;
+    (on_frame.borrow_mut())(&screen.borrow());
+    Ok(screen.into_inner())
+}
+
+/// Run a game without showing it, e.g. to count the tiles or get the final score
+pub fn run_headless<P: JoystickPolicy>(intcode: &str, policy: P) -> Result<Screen, ArcadeError> {
+    run(intcode, policy, |_| {})
+}
+
+/// Run a game on the terminal, redrawing the screen every frame
+pub fn run_on_terminal<P: JoystickPolicy>(intcode: &str, policy: P) -> Result<Screen, ArcadeError> {
+    run(intcode, policy, |screen| println!("\x1b[H\x1b[2J{}", screen.render()))
+}
@@ -0,0 +1,156 @@
+//! Adapter for programs which talk in ASCII: output values are collected into lines of text and
+//! commands are fed to the program as newline-terminated character codes.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use crate::{Integer, IntcodeError, Program};
+
+const NEWLINE: Integer = '\n' as Integer;
+const MAX_ASCII: Integer = 127;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsciiOutput {
+    /// Line of text, without the trailing newline
+    Line(String),
+    /// Value outside of the ASCII range, passed through as a number
+    Value(Integer),
+}
+
+/// Character codes for a command, including the terminating newline
+pub fn encode_line(line: &str) -> Vec<Integer> {
+    line.chars()
+        .map(|c| c as Integer)
+        .chain(std::iter::once(NEWLINE))
+        .collect()
+}
+
+/// Collects output values into lines of text
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    line: String,
+}
+
+impl LineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an output value, returning a line once it's complete or the value itself if it isn't
+    /// ASCII
+    pub fn push(&mut self, val: Integer) -> Option<AsciiOutput> {
+        match val {
+            NEWLINE => Some(AsciiOutput::Line(std::mem::take(&mut self.line))),
+            0..=MAX_ASCII => {
+                self.line.push(val as u8 as char);
+                None
+            }
+            _ => Some(AsciiOutput::Value(val)),
+        }
+    }
+
+    /// Text received since the last complete line, e.g. a prompt
+    pub fn finish(&mut self) -> Option<AsciiOutput> {
+        if self.line.is_empty() {
+            None
+        } else {
+            Some(AsciiOutput::Line(std::mem::take(&mut self.line)))
+        }
+    }
+}
+
+/// Input and output functions for an ASCII program, for running it with a `Program` of your own.
+/// Whenever the program wants input and the previous command has been used up, `next_command`
+/// is asked for another line; returning None ends the input. Decoded output is passed to
+/// `on_output`.
+pub struct AsciiIo<C, H> {
+    next_command: RefCell<C>,
+    on_output: RefCell<H>,
+    decoder: RefCell<LineDecoder>,
+    pending: RefCell<VecDeque<Integer>>,
+}
+
+impl<C, H> AsciiIo<C, H>
+    where C: FnMut() -> Option<String>, H: FnMut(AsciiOutput)
+{
+    pub fn new(next_command: C, on_output: H) -> Self {
+        Self {
+            next_command: RefCell::new(next_command),
+            on_output: RefCell::new(on_output),
+            decoder: RefCell::new(LineDecoder::new()),
+            pending: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Values to give the program as they are before asking for any commands
+    pub fn with_values<V: IntoIterator<Item = Integer>>(self, values: V) -> Self {
+        self.pending.borrow_mut().extend(values);
+        self
+    }
+
+    /// Next input value, for the program's input function
+    pub fn input(&self) -> Option<Integer> {
+        if self.pending.borrow().is_empty() {
+//            Flush a prompt which doesn't end with a newline before waiting for the command
+            self.finish();
+            let command = (self.next_command.borrow_mut())()?;
+            self.pending.borrow_mut().extend(encode_line(&command));
+        }
+        self.pending.borrow_mut().pop_front()
+    }
+
+    /// Take an output value, for the program's output function
+    pub fn output(&self, val: Integer) {
+        let output = self.decoder.borrow_mut().push(val);
+        if let Some(output) = output {
+            (self.on_output.borrow_mut())(output);
+        }
+    }
+
+    /// Pass on text output since the last complete line, e.g. once the program has stopped
+    pub fn finish(&self) {
+        let partial = self.decoder.borrow_mut().finish();
+        if let Some(partial) = partial {
+            (self.on_output.borrow_mut())(partial);
+        }
+    }
+}
+
+/// Run an ASCII program, with commands and output handled as described for `AsciiIo`
+pub fn run<C, H>(intcode: &str, next_command: C, on_output: H) -> Result<(), IntcodeError>
+    where C: FnMut() -> Option<String>, H: FnMut(AsciiOutput)
+{
+    let io = AsciiIo::new(next_command, on_output);
+    let mut program = Program::new(intcode, || io.input(), |val| io.output(val));
+    program.execute().result()?;
+    io.finish();
+    Ok(())
+}
+
+/// Run an ASCII program with the given commands, collecting everything it outputs
+pub fn run_scripted(intcode: &str, commands: &[String]) -> Result<Vec<AsciiOutput>, IntcodeError> {
+    let mut commands = commands.iter().cloned();
+    let mut outputs = Vec::new();
+    run(intcode, || commands.next(), |output| outputs.push(output))?;
+    Ok(outputs)
+}
+
+/// Commands for `run_scripted`, one per line
+pub fn read_script<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents.lines().map(|line| line.to_owned()).collect())
+}
+
+/// Run an ASCII program on the terminal, reading commands from stdin until it's closed
+pub fn run_interactive(intcode: &str) -> Result<(), IntcodeError> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    run(intcode,
+        || lines.next().and_then(|line| line.ok()),
+        |output| match output {
+            AsciiOutput::Line(line) => println!("{}", line),
+            AsciiOutput::Value(val) => println!("{}", val),
+        })
+}
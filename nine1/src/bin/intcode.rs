@@ -0,0 +1,229 @@
+use nine1::ascii::{AsciiIo, AsciiOutput};
+use nine1::lint::lint;
+use nine1::loader::{self, LoadError};
+use nine1::optimizer::optimize;
+use nine1::{replay, Extensions, Integer, Program, Session};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::process;
+
+const USAGE: &str = "Usage: intcode [OPTIONS] [PROGRAM]
+
+Runs the intcode program in the file PROGRAM, or from stdin if PROGRAM is - or missing. The program
+can be intcode text or a binary image.
+
+Options:
+    -i, --input VALUES     Comma-separated input values, may be given more than once
+    -f, --input-file FILE  Read input values from FILE (lines of text with --ascii)
+    -I, --interactive      Read further input from stdin once the other inputs run out
+    -a, --ascii            Print output as ASCII text and encode text input as characters
+    -c, --cycles           Report the number of cycles executed on stderr
+    -p, --report           Report cycles, instruction counts, memory use and why the run stopped
+                           on stderr
+    -m, --memory           Print the final memory on stderr
+    -s, --snapshot FILE    Save the final memory to FILE, e.g. to compare runs with memdiff
+    -l, --lint             Check the code reachable from the start for problems, and only run
+                           the program if there aren't any
+    -O, --optimize         Optimize the program before running it and report the changes on stderr
+    -r, --record FILE      Save the inputs and outputs of the run to FILE
+    -R, --replay FILE      Run with the inputs saved in FILE and check the outputs match
+    -h, --help             Show this message";
+
+#[derive(Default)]
+struct Options {
+    program: Option<String>,
+    inputs: Vec<Integer>,
+    input_file: Option<String>,
+    interactive: bool,
+    ascii: bool,
+    cycles: bool,
+    report: bool,
+    memory: bool,
+    snapshot: Option<String>,
+    lint: bool,
+    optimize: bool,
+    record: Option<String>,
+    replay: Option<String>,
+}
+
+fn parse_values(text: &str) -> Result<Vec<Integer>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse::<Integer>().map_err(|_| format!("Invalid input value: {}", x)))
+        .collect()
+}
+
+fn parse_args<A: Iterator<Item = String>>(mut args: A) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-i" | "--input" => {
+                let values = args.next().ok_or("Missing values for --input")?;
+                options.inputs.extend(parse_values(&values)?);
+            }
+            "-f" | "--input-file" => {
+                options.input_file = Some(args.next().ok_or("Missing file for --input-file")?);
+            }
+            "-I" | "--interactive" => options.interactive = true,
+            "-a" | "--ascii" => options.ascii = true,
+            "-c" | "--cycles" => options.cycles = true,
+            "-p" | "--report" => options.report = true,
+            "-m" | "--memory" => options.memory = true,
+            "-s" | "--snapshot" => {
+                options.snapshot = Some(args.next().ok_or("Missing file for --snapshot")?);
+            }
+            "-l" | "--lint" => options.lint = true,
+            "-O" | "--optimize" => options.optimize = true,
+            "-r" | "--record" => {
+                options.record = Some(args.next().ok_or("Missing file for --record")?);
+            }
+            "-R" | "--replay" => {
+                options.replay = Some(args.next().ok_or("Missing file for --replay")?);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("Unknown option: {}", arg)),
+            _ if options.program.is_some() => return Err(format!("Unexpected argument: {}", arg)),
+            _ => options.program = Some(arg),
+        }
+    }
+    let program_from_stdin = options.program.as_deref().unwrap_or("-") == "-";
+    if program_from_stdin && options.interactive {
+        return Err("Can't read both the program and interactive input from stdin".to_owned());
+    }
+    let has_inputs = !options.inputs.is_empty() || options.input_file.is_some() || options.interactive;
+    if options.replay.is_some() && (has_inputs || options.record.is_some()) {
+        return Err("--replay takes its inputs from the session file".to_owned());
+    }
+    Ok(options)
+}
+
+fn fail(message: &str, code: i32) -> ! {
+    eprintln!("{}", message);
+    process::exit(code)
+}
+
+fn read_program(path: Option<&str>) -> Result<Vec<Integer>, LoadError> {
+    match path {
+        None | Some("-") => loader::read_from(io::stdin()),
+        Some(path) => loader::load(path),
+    }
+}
+
+fn print_output(output: AsciiOutput) {
+    match output {
+        AsciiOutput::Line(line) => println!("{}", line),
+        AsciiOutput::Value(val) => println!("{}", val),
+    }
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1))
+        .unwrap_or_else(|err| fail(&format!("{}\n\n{}", err, USAGE), 2));
+    let memory = read_program(options.program.as_deref())
+        .unwrap_or_else(|err| fail(&format!("Couldn't read program: {}", err), 2));
+    if options.lint {
+        let diagnostics = lint(&memory, &Extensions::new());
+        for diagnostic in diagnostics.iter() {
+            eprintln!("{}", diagnostic);
+        }
+        if !diagnostics.is_empty() {
+            process::exit(1);
+        }
+    }
+    let memory = if options.optimize {
+        let optimized = optimize(&memory);
+        if let Some(reason) = &optimized.not_optimized {
+            eprintln!("Not optimized: {}", reason);
+        }
+        for change in optimized.changes.iter() {
+            eprintln!("Optimized {}", change);
+        }
+        optimized.memory
+    } else {
+        memory
+    };
+
+    if let Some(path) = &options.replay {
+        let session = Session::load(path)
+            .unwrap_or_else(|err| fail(&format!("Couldn't read session: {}", err), 2));
+        match replay(&memory, &session) {
+            Ok(()) => eprintln!("Replay matched {} events", session.events.len()),
+            Err(divergence) => fail(&divergence.to_string(), 1),
+        }
+        return;
+    }
+
+    let mut inputs: VecDeque<Integer> = options.inputs.iter().cloned().collect();
+    let mut commands = VecDeque::new();
+    if let Some(path) = &options.input_file {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|err| fail(&format!("Couldn't read input file: {}", err), 2));
+        if options.ascii {
+            commands.extend(contents.lines().map(|line| line.to_owned()));
+        } else {
+            inputs.extend(parse_values(&contents).unwrap_or_else(|err| fail(&err, 2)));
+        }
+    }
+
+    let stdin = io::stdin();
+    let stdin_lines = RefCell::new(stdin.lock().lines());
+    let read_line = || if options.interactive {
+        stdin_lines.borrow_mut().next().and_then(|line| line.ok())
+    } else {
+        None
+    };
+//    Values given with --input go to ASCII programs as they are, before any commands
+    let values = if options.ascii { inputs.drain(..).collect() } else { Vec::new() };
+    let ascii = AsciiIo::new(|| commands.pop_front().or_else(read_line), print_output).with_values(values);
+    let inputs = RefCell::new(inputs);
+    let next_value = || {
+        while inputs.borrow().is_empty() {
+            let line = read_line()?;
+            match parse_values(&line) {
+                Ok(values) => inputs.borrow_mut().extend(values),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        inputs.borrow_mut().pop_front()
+    };
+
+    let mut program = Program::from_memory(
+        memory,
+        || if options.ascii { ascii.input() } else { next_value() },
+        |val| if options.ascii { ascii.output(val) } else { println!("{}", val) });
+    if options.record.is_some() {
+        program = program.with_recording();
+    }
+    let report = program.execute();
+    let result = report.result();
+    ascii.finish();
+
+    if options.cycles {
+        eprintln!("Cycles: {}", program.cycles());
+    }
+    if options.report {
+        eprintln!("{}", report);
+    }
+//    Leave out the zeroed memory after the last used cell
+    let used = program.memory().to_vec();
+    if options.memory {
+        eprintln!("Memory: {}", loader::to_text(&used));
+    }
+    if let Some(path) = &options.snapshot {
+        fs::write(path, loader::to_text(&used) + "\n")
+            .unwrap_or_else(|err| fail(&format!("Couldn't save snapshot: {}", err), 2));
+    }
+    if let (Some(path), Some(session)) = (&options.record, program.session()) {
+        session.save(path)
+            .unwrap_or_else(|err| fail(&format!("Couldn't save session: {}", err), 2));
+    }
+    if let Err(err) = result {
+        fail(&format!("Error: {}", err), 1);
+    }
+}
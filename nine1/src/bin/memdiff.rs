@@ -0,0 +1,42 @@
+use nine1::loader;
+use nine1::{code_addresses, diff_memory, Extensions};
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: memdiff OLD NEW
+
+Compares two memory snapshots, such as those saved by intcode --snapshot, and lists the ranges of
+addresses which changed. Each range is marked as code or data, going by a disassembly of OLD.
+Exits with 1 if there are any changes.
+
+Options:
+    -h, --help    Show this message";
+
+fn fail(message: &str, code: i32) -> ! {
+    eprintln!("{}", message);
+    process::exit(code)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    if args.len() != 2 {
+        fail(&format!("Expected 2 snapshot files\n\n{}", USAGE), 2);
+    }
+    let load = |path: &str| loader::load(path)
+        .unwrap_or_else(|err| fail(&format!("Couldn't read {}: {}", path, err), 2));
+    let old = load(&args[0]);
+    let new = load(&args[1]);
+
+    let code = code_addresses(&old, None, &Extensions::new());
+    let diff = diff_memory(&old, &new, &code);
+    if diff.is_empty() {
+        return;
+    }
+    print!("{}", diff);
+    println!("{} addresses changed in {} ranges", diff.changed(), diff.ranges.len());
+    process::exit(1);
+}
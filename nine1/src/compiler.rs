@@ -0,0 +1,767 @@
+//! Compiler from a tiny language to intcode, for writing test programs that do more than the
+//! puzzle examples.
+//!
+//! ```text
+//! fn fib(n) {
+//!     if n < 2 {
+//!         return n;
+//!     }
+//!     return fib(n - 1) + fib(n - 2);
+//! }
+//!
+//! let n = input();
+//! while n > 0 {
+//!     output(fib(n));
+//!     n = n - 1;
+//! }
+//! ```
+//!
+//! All values are integers. Expressions support `+ - *`, comparisons (`< <= > >= == !=`, giving
+//! 1 or 0), `!`, unary `-`, short-circuiting `&&` and `||`, `input()` and function calls.
+//! Statements are `let`, assignment, `if`/`else`, `while`, `return`, `output(expr);` and
+//! expression statements. `//` starts a comment.
+//!
+//! Statements outside of functions make up the main program. Variables are local to the function
+//! (or main program) they're declared in and scoped to their block.
+//!
+//! Functions use the same calling convention as the BOOST program: the relative base is the stack
+//! pointer and each function allocates its frame with a `RelativeBaseOffset` on entry. The caller
+//! stores the return address at relative address 0 and the arguments at 1 onwards, then jumps to
+//! the function. The callee leaves its result at relative address 1 of the caller's frame before
+//! restoring the relative base and jumping back.
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use nine1::{compiler, Program};
+//!
+//! let intcode = compiler::compile("
+//!     fn square(x) { return x * x; }
+//!     output(square(input()));
+//! ").unwrap();
+//! let outputs = RefCell::new(Vec::new());
+//! Program::new(&intcode, || Some(12), |val| outputs.borrow_mut().push(val)).execute().result().unwrap();
+//! assert_eq!(*outputs.borrow(), vec![144]);
+//! ```
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Instruction, Integer, ParamMode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for CompileError {}
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+fn error<T>(line: usize, message: String) -> Result<T> {
+    Err(CompileError { line, message })
+}
+
+/// Compile source code to intcode text which can be given to `Program::new`
+pub fn compile(source: &str) -> Result<String> {
+    let tokens = tokenize(source)?;
+    let (functions, main) = Parser { tokens, pos: 0 }.parse_program()?;
+    let code = generate(&functions, &main)?;
+    Ok(code.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(","))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(Integer),
+    Ident(String),
+    Punct(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Num(val) => write!(f, "{}", val),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Punct(punct) => write!(f, "{}", punct),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Longest first, so that e.g. `<=` isn't lexed as `<` and `=`
+const PUNCTUATION: [&str; 19] = [
+    "<=", ">=", "==", "!=", "&&", "||",
+    "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "<", ">", "!",
+];
+
+const KEYWORDS: [&str; 8] = ["fn", "let", "if", "else", "while", "return", "input", "output"];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line_num = i + 1;
+        let line = line.split("//").next().unwrap();
+        let mut rest = line.trim_start();
+        while let Some(c) = rest.chars().next() {
+            let len = if c.is_ascii_digit() {
+                let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                let val = rest[..len].parse()
+                    .or_else(|_| error(line_num, format!("Number {} is too large", &rest[..len])))?;
+                tokens.push((Token::Num(val), line_num));
+                len
+            } else if c.is_alphabetic() || c == '_' {
+                let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                tokens.push((Token::Ident(rest[..len].to_owned()), line_num));
+                len
+            } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(*punct)) {
+                tokens.push((Token::Punct(punct), line_num));
+                punct.len()
+            } else {
+                return error(line_num, format!("Unexpected character {:?}", c));
+            };
+            rest = rest[len..].trim_start();
+        }
+    }
+    let last_line = source.lines().count().max(1);
+    tokens.push((Token::End, last_line));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(Integer),
+    Var { name: String, line: usize },
+    Input,
+    Call { name: String, args: Vec<Expr>, line: usize },
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stmt {
+    Let { name: String, value: Expr, line: usize },
+    Assign { name: String, value: Expr, line: usize },
+    If { cond: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt> },
+    While { cond: Expr, body: Vec<Stmt> },
+    Return { value: Option<Expr>, line: usize },
+    Output(Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    line: usize,
+}
+
+/// Binary operators from lowest to highest precedence
+const PRECEDENCE: [&[(&str, BinOp)]; 5] = [
+    &[("||", BinOp::Or)],
+    &[("&&", BinOp::And)],
+    &[
+        ("<", BinOp::Less), ("<=", BinOp::LessEqual), (">", BinOp::Greater),
+        (">=", BinOp::GreaterEqual), ("==", BinOp::Equal), ("!=", BinOp::NotEqual),
+    ],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[("*", BinOp::Mul)],
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Token::Punct(p) if *p == punct)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(name) if name == keyword)
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T> {
+        error(self.line(), format!("Expected {} but found {}", expected, self.peek()))
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<()> {
+        if self.is_punct(punct) {
+            self.next();
+            Ok(())
+        } else {
+            self.unexpected(&format!("`{}`", punct))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek().clone() {
+            Token::Ident(name) if !KEYWORDS.contains(&name.as_str()) => {
+                self.next();
+                Ok(name)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn parse_program(mut self) -> Result<(Vec<Function>, Vec<Stmt>)> {
+        let mut functions = Vec::new();
+        let mut main = Vec::new();
+        while *self.peek() != Token::End {
+            if self.is_keyword("fn") {
+                functions.push(self.function()?);
+            } else {
+                main.push(self.statement()?);
+            }
+        }
+        Ok((functions, main))
+    }
+
+    fn function(&mut self) -> Result<Function> {
+        let line = self.line();
+        self.next();
+        let name = self.ident()?;
+        self.expect("(")?;
+        let mut params = Vec::new();
+        while !self.is_punct(")") {
+            if !params.is_empty() {
+                self.expect(",")?;
+            }
+            params.push(self.ident()?);
+        }
+        self.next();
+        let body = self.block()?;
+        Ok(Function { name, params, body, line })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect("{")?;
+        let mut body = Vec::new();
+        while !self.is_punct("}") {
+            if *self.peek() == Token::End {
+                return self.unexpected("`}`");
+            }
+            body.push(self.statement()?);
+        }
+        self.next();
+        Ok(body)
+    }
+
+    fn statement(&mut self) -> Result<Stmt> {
+        let line = self.line();
+        let stmt = if self.is_keyword("let") {
+            self.next();
+            let name = self.ident()?;
+            self.expect("=")?;
+            Stmt::Let { name, value: self.expr()?, line }
+        } else if self.is_keyword("if") {
+            return self.if_statement();
+        } else if self.is_keyword("while") {
+            self.next();
+            let cond = self.expr()?;
+            return Ok(Stmt::While { cond, body: self.block()? });
+        } else if self.is_keyword("return") {
+            self.next();
+            let value = if self.is_punct(";") { None } else { Some(self.expr()?) };
+            Stmt::Return { value, line }
+        } else if self.is_keyword("output") {
+            self.next();
+            self.expect("(")?;
+            let value = self.expr()?;
+            self.expect(")")?;
+            Stmt::Output(value)
+        } else if matches!(self.tokens.get(self.pos + 1), Some((Token::Punct("="), _))) {
+            let name = self.ident()?;
+            self.next();
+            Stmt::Assign { name, value: self.expr()?, line }
+        } else {
+            Stmt::Expr(self.expr()?)
+        };
+        self.expect(";")?;
+        Ok(stmt)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt> {
+        self.next();
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if self.is_keyword("else") {
+            self.next();
+            if self.is_keyword("if") {
+                vec![self.if_statement()?]
+            } else {
+                self.block()?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Stmt::If { cond, then, otherwise })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(&(_, op)) = PRECEDENCE[level].iter().find(|(punct, _)| self.is_punct(punct)) {
+            self.next();
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.is_punct("-") {
+            self.next();
+            Ok(Expr::Unary(UnOp::Neg, Box::new(self.unary()?)))
+        } else if self.is_punct("!") {
+            self.next();
+            Ok(Expr::Unary(UnOp::Not, Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let line = self.line();
+        match self.peek().clone() {
+            Token::Num(val) => {
+                self.next();
+                Ok(Expr::Num(val))
+            }
+            Token::Punct("(") => {
+                self.next();
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Ident(name) if name == "input" => {
+                self.next();
+                self.expect("(")?;
+                self.expect(")")?;
+                Ok(Expr::Input)
+            }
+            Token::Ident(_) => {
+                let name = self.ident()?;
+                if !self.is_punct("(") {
+                    return Ok(Expr::Var { name, line });
+                }
+                self.next();
+                let mut args = Vec::new();
+                while !self.is_punct(")") {
+                    if !args.is_empty() {
+                        self.expect(",")?;
+                    }
+                    args.push(self.expr()?);
+                }
+                self.next();
+                Ok(Expr::Call { name, args, line })
+            }
+            _ => self.unexpected("an expression"),
+        }
+    }
+}
+
+type Label = usize;
+
+/// Instruction parameter before the frame layout is known
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Imm(Integer),
+    /// Immediate address of a label
+    Label(Label),
+    /// Relative to the relative base, i.e. the end of the current frame
+    Rel(Integer),
+    /// Frame slot of a named variable
+    Var(usize),
+    /// Frame slot of a temporary, allocated after all the variables
+    Temp(usize),
+    /// Size of the current frame, multiplied by the given sign
+    FrameSize(Integer),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Label(Label),
+    Op(Instruction, Vec<Operand>),
+}
+
+const RETURN_ADDRESS: usize = 0;
+/// Slot which holds the first argument and the return value
+const RESULT: usize = 1;
+
+/// Code generator for one function, or the main program
+struct FunctionGen<'a> {
+    signatures: &'a HashMap<String, (Label, usize)>,
+    next_label: &'a mut Label,
+    items: Vec<Item>,
+    scopes: Vec<HashMap<String, usize>>,
+    num_vars: usize,
+    num_temps: usize,
+    max_temps: usize,
+    is_main: bool,
+}
+
+impl<'a> FunctionGen<'a> {
+    fn label(&mut self) -> Label {
+        *self.next_label += 1;
+        *self.next_label
+    }
+
+    fn emit(&mut self, instruction: Instruction, params: Vec<Operand>) {
+        self.items.push(Item::Op(instruction, params));
+    }
+
+    fn copy(&mut self, from: Operand, to: Operand) {
+        self.emit(Instruction::Add, vec![from, Operand::Imm(0), to]);
+    }
+
+    fn jump(&mut self, target: Operand) {
+        self.emit(Instruction::JumpIfTrue, vec![Operand::Imm(1), target]);
+    }
+
+    fn temp(&mut self) -> Operand {
+        self.num_temps += 1;
+        self.max_temps = self.max_temps.max(self.num_temps);
+        Operand::Temp(self.num_temps - 1)
+    }
+
+    fn declare(&mut self, name: &str, line: usize) -> Result<usize> {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.contains_key(name) {
+            return error(line, format!("Variable {} is already declared", name));
+        }
+        scope.insert(name.to_owned(), self.num_vars);
+        self.num_vars += 1;
+        Ok(self.num_vars - 1)
+    }
+
+    fn lookup(&self, name: &str, line: usize) -> Result<Operand> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(&slot) => Ok(Operand::Var(slot)),
+            None => error(line, format!("Unknown variable {}", name)),
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) -> Result<()> {
+        self.scopes.push(HashMap::new());
+        for stmt in body {
+            self.statement(stmt)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<()> {
+//        Temporaries only live until the end of the statement which needs them
+        self.num_temps = 0;
+        match stmt {
+            Stmt::Let { name, value, line } => {
+                let value = self.expr(value)?;
+                let slot = self.declare(name, *line)?;
+                self.copy(value, Operand::Var(slot));
+            }
+            Stmt::Assign { name, value, line } => {
+                let var = self.lookup(name, *line)?;
+                let value = self.expr(value)?;
+                self.copy(value, var);
+            }
+            Stmt::If { cond, then, otherwise } => {
+                let cond = self.expr(cond)?;
+                let else_label = self.label();
+                let end_label = self.label();
+                self.emit(Instruction::JumpIfFalse, vec![cond, Operand::Label(else_label)]);
+                self.block(then)?;
+                self.jump(Operand::Label(end_label));
+                self.items.push(Item::Label(else_label));
+                self.block(otherwise)?;
+                self.items.push(Item::Label(end_label));
+            }
+            Stmt::While { cond, body } => {
+                let start_label = self.label();
+                let end_label = self.label();
+                self.items.push(Item::Label(start_label));
+                let cond = self.expr(cond)?;
+                self.emit(Instruction::JumpIfFalse, vec![cond, Operand::Label(end_label)]);
+                self.block(body)?;
+                self.jump(Operand::Label(start_label));
+                self.items.push(Item::Label(end_label));
+            }
+            Stmt::Return { value, line } => {
+                if self.is_main {
+                    return error(*line, "return outside of a function".to_owned());
+                }
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Operand::Imm(0),
+                };
+                self.ret(value);
+            }
+            Stmt::Output(value) => {
+                let value = self.expr(value)?;
+                self.emit(Instruction::Output, vec![value]);
+            }
+            Stmt::Expr(value) => {
+                self.expr(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self, value: Operand) {
+        self.copy(value, Operand::Var(RESULT));
+        self.emit(Instruction::RelativeBaseOffset, vec![Operand::FrameSize(-1)]);
+        self.jump(Operand::Rel(RETURN_ADDRESS as Integer));
+    }
+
+    /// Operand holding the value of the expression
+    fn expr(&mut self, expr: &Expr) -> Result<Operand> {
+        Ok(match expr {
+            Expr::Num(val) => Operand::Imm(*val),
+            Expr::Var { name, line } => self.lookup(name, *line)?,
+            Expr::Input => {
+                let dest = self.temp();
+                self.emit(Instruction::Input, vec![dest]);
+                dest
+            }
+            Expr::Call { name, args, line } => self.call(name, args, *line)?,
+            Expr::Unary(op, value) => {
+                let value = self.expr(value)?;
+                let dest = self.temp();
+                match op {
+                    UnOp::Neg => self.emit(Instruction::Multiply, vec![value, Operand::Imm(-1), dest]),
+                    UnOp::Not => self.emit(Instruction::Equals, vec![value, Operand::Imm(0), dest]),
+                }
+                dest
+            }
+            Expr::Binary(op @ (BinOp::And | BinOp::Or), lhs, rhs) => {
+//                Result is the left side's truth value unless the right side has to be evaluated
+                let dest = self.temp();
+                let end_label = self.label();
+                let lhs = self.expr(lhs)?;
+                self.emit(Instruction::Equals, vec![lhs, Operand::Imm(0), dest]);
+                self.emit(Instruction::Equals, vec![dest, Operand::Imm(0), dest]);
+                let skip = if *op == BinOp::And { Instruction::JumpIfFalse } else { Instruction::JumpIfTrue };
+                self.emit(skip, vec![dest, Operand::Label(end_label)]);
+                let rhs = self.expr(rhs)?;
+                self.emit(Instruction::Equals, vec![rhs, Operand::Imm(0), dest]);
+                self.emit(Instruction::Equals, vec![dest, Operand::Imm(0), dest]);
+                self.items.push(Item::Label(end_label));
+                dest
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                let dest = self.temp();
+                self.binary(*op, lhs, rhs, dest);
+                dest
+            }
+        })
+    }
+
+    fn binary(&mut self, op: BinOp, lhs: Operand, rhs: Operand, dest: Operand) {
+        use Instruction::*;
+        match op {
+            BinOp::Add => self.emit(Add, vec![lhs, rhs, dest]),
+            BinOp::Sub => {
+                self.emit(Multiply, vec![rhs, Operand::Imm(-1), dest]);
+                self.emit(Add, vec![lhs, dest, dest]);
+            }
+            BinOp::Mul => self.emit(Multiply, vec![lhs, rhs, dest]),
+            BinOp::Less => self.emit(LessThan, vec![lhs, rhs, dest]),
+            BinOp::Greater => self.emit(LessThan, vec![rhs, lhs, dest]),
+            BinOp::LessEqual => {
+                self.emit(LessThan, vec![rhs, lhs, dest]);
+                self.emit(Equals, vec![dest, Operand::Imm(0), dest]);
+            }
+            BinOp::GreaterEqual => {
+                self.emit(LessThan, vec![lhs, rhs, dest]);
+                self.emit(Equals, vec![dest, Operand::Imm(0), dest]);
+            }
+            BinOp::Equal => self.emit(Equals, vec![lhs, rhs, dest]),
+            BinOp::NotEqual => {
+                self.emit(Equals, vec![lhs, rhs, dest]);
+                self.emit(Equals, vec![dest, Operand::Imm(0), dest]);
+            }
+            BinOp::And | BinOp::Or => unreachable!("Short-circuiting operators are handled by expr"),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<Operand> {
+        let &(label, num_params) = self.signatures.get(name)
+            .map_or_else(|| error(line, format!("Unknown function {}", name)), Ok)?;
+        if args.len() != num_params {
+            return error(line, format!("{} takes {} arguments but was given {}", name, num_params, args.len()));
+        }
+//        Evaluate all arguments before setting up the call, as they may contain calls themselves
+        let args = args.iter().map(|arg| self.expr(arg)).collect::<Result<Vec<_>>>()?;
+        for (i, arg) in args.into_iter().enumerate() {
+            self.copy(arg, Operand::Rel((RESULT + i) as Integer));
+        }
+        let return_label = self.label();
+        self.copy(Operand::Label(return_label), Operand::Rel(RETURN_ADDRESS as Integer));
+        self.jump(Operand::Label(label));
+        self.items.push(Item::Label(return_label));
+        let dest = self.temp();
+        self.copy(Operand::Rel(RESULT as Integer), dest);
+        Ok(dest)
+    }
+
+    /// Code with all frame slots turned into offsets from the relative base
+    fn finish(self) -> Vec<Item> {
+        let frame_size = (self.num_vars + self.max_temps) as Integer;
+        let num_vars = self.num_vars;
+        let resolve = |operand: Operand| match operand {
+            Operand::Var(slot) => Operand::Rel(slot as Integer - frame_size),
+            Operand::Temp(slot) => Operand::Rel((num_vars + slot) as Integer - frame_size),
+            Operand::FrameSize(sign) => Operand::Imm(sign * frame_size),
+            operand => operand,
+        };
+        self.items.into_iter().map(|item| match item {
+            Item::Op(instruction, params) => Item::Op(instruction, params.into_iter().map(resolve).collect()),
+            label => label,
+        }).collect()
+    }
+}
+
+fn generate(functions: &[Function], main: &[Stmt]) -> Result<Vec<Integer>> {
+    let mut next_label = 0;
+    let stack_label = 0;
+    let mut signatures = HashMap::new();
+    for function in functions {
+        next_label += 1;
+        if signatures.insert(function.name.clone(), (next_label, function.params.len())).is_some() {
+            return error(function.line, format!("Function {} is already defined", function.name));
+        }
+    }
+
+    let mut items = vec![Item::Op(Instruction::RelativeBaseOffset, vec![Operand::Label(stack_label)])];
+    let mut gen = FunctionGen {
+        signatures: &signatures,
+        next_label: &mut next_label,
+        items: vec![Item::Op(Instruction::RelativeBaseOffset, vec![Operand::FrameSize(1)])],
+        scopes: vec![HashMap::new()],
+        num_vars: 0,
+        num_temps: 0,
+        max_temps: 0,
+        is_main: true,
+    };
+    for stmt in main {
+        gen.statement(stmt)?;
+    }
+    gen.emit(Instruction::Halt, vec![]);
+    items.extend(gen.finish());
+
+    for function in functions {
+        let (label, _) = signatures[&function.name];
+        let mut gen = FunctionGen {
+            signatures: &signatures,
+            next_label: &mut next_label,
+            items: vec![
+                Item::Label(label),
+                Item::Op(Instruction::RelativeBaseOffset, vec![Operand::FrameSize(1)]),
+            ],
+            scopes: vec![HashMap::new()],
+            num_vars: RESULT,
+            num_temps: 0,
+            max_temps: 0,
+            is_main: false,
+        };
+        for param in function.params.iter() {
+            gen.declare(param, function.line)?;
+        }
+//        Even without params there needs to be a slot for the result
+        gen.num_vars = gen.num_vars.max(RESULT + 1);
+        gen.block(&function.body)?;
+        gen.ret(Operand::Imm(0));
+        items.extend(gen.finish());
+    }
+    items.push(Item::Label(stack_label));
+    Ok(assemble(&items))
+}
+
+fn assemble(items: &[Item]) -> Vec<Integer> {
+    let mut addresses = HashMap::new();
+    let mut pos = 0;
+    for item in items {
+        match item {
+            Item::Label(label) => {
+                addresses.insert(*label, pos);
+            }
+            Item::Op(_, params) => pos += 1 + params.len() as Integer,
+        }
+    }
+
+    let mut code = Vec::new();
+    for item in items {
+        if let Item::Op(instruction, params) = item {
+            let mut opcode = *instruction as Integer;
+            let mut digit = 100;
+            let mut values = Vec::new();
+            for param in params {
+                let (mode, value) = match param {
+                    Operand::Imm(val) => (ParamMode::IMMEDIATE, *val),
+                    Operand::Label(label) => (ParamMode::IMMEDIATE, addresses[label]),
+                    Operand::Rel(offset) => (ParamMode::RELATIVE, *offset),
+                    _ => unreachable!("Frame slots are resolved before assembling"),
+                };
+                opcode += mode as Integer * digit;
+                digit *= 10;
+                values.push(value);
+            }
+            code.push(opcode);
+            code.extend(values);
+        }
+    }
+    code
+}
@@ -0,0 +1,143 @@
+//! Which instructions and branch directions a program exercised.
+//!
+//! Coverage from several runs of the same program can be merged, e.g. to see what all phase
+//! setting permutations of an amplifier program touch between them:
+//!
+//! ```
+//! use nine1::{Coverage, Program};
+//!
+//! let intcode = "3,9,8,9,10,9,4,9,99,-1,8";
+//! let mut total = Coverage::new();
+//! for input in 7..=8 {
+//!     let mut program = Program::new(intcode, || Some(input), |_| {}).with_coverage();
+//!     program.execute().result().unwrap();
+//!     total.merge(program.coverage().unwrap());
+//! }
+//! assert!(total.executed(8));
+//! ```
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::{disassemble, Extensions, Integer, Line, Position};
+
+/// How often a conditional jump did and didn't jump
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BranchCounts {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    hits: BTreeMap<Position, u64>,
+    branches: BTreeMap<Position, BranchCounts>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record_instruction(&mut self, pos: Position) {
+        *self.hits.entry(pos).or_insert(0) += 1;
+    }
+
+    pub(crate) fn record_branch(&mut self, pos: Position, taken: bool) {
+        let counts = self.branches.entry(pos).or_default();
+        if taken {
+            counts.taken += 1;
+        } else {
+            counts.not_taken += 1;
+        }
+    }
+
+    /// Whether an instruction starting at pos was executed
+    pub fn executed(&self, pos: Position) -> bool {
+        self.hits.contains_key(&pos)
+    }
+
+    /// Positions of the instructions which were executed
+    pub(crate) fn executed_positions(&self) -> impl Iterator<Item = Position> + '_ {
+        self.hits.keys().cloned()
+    }
+
+    /// Number of times an instruction starting at pos was executed
+    pub fn hits(&self, pos: Position) -> u64 {
+        self.hits.get(&pos).cloned().unwrap_or(0)
+    }
+
+    /// Counts for the JumpIfTrue or JumpIfFalse at pos, if it was executed
+    pub fn branch(&self, pos: Position) -> Option<BranchCounts> {
+        self.branches.get(&pos).cloned()
+    }
+
+    /// Add the counts from another run of the same program
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&pos, &hits) in other.hits.iter() {
+            *self.hits.entry(pos).or_insert(0) += hits;
+        }
+        for (&pos, counts) in other.branches.iter() {
+            let ours = self.branches.entry(pos).or_default();
+            ours.taken += counts.taken;
+            ours.not_taken += counts.not_taken;
+        }
+    }
+
+    /// Disassembly of the program, leaving out the zeroed memory at the end which was never
+    /// executed
+    fn lines(&self, memory: &[Integer], extensions: &Extensions) -> Vec<Line> {
+        let last_used = memory.iter().rposition(|&val| val != 0).map_or(0, |last| last + 1);
+        let last_executed = self.hits.keys().next_back().map_or(0, |&pos| pos as usize + 1);
+        let end = last_used.max(last_executed).min(memory.len());
+        disassemble(&memory[..end], extensions)
+    }
+
+    /// Disassembly of the program with the hit count in front of each instruction and branch
+    /// counts after each conditional jump. Instructions that never ran are marked with #####.
+    pub fn annotate(&self, memory: &[Integer], extensions: &Extensions) -> String {
+        let mut report = String::new();
+        for line in self.lines(memory, extensions) {
+            let pos = line.pos();
+            let count = match (&line, self.hits.get(&pos)) {
+                (_, Some(hits)) => hits.to_string(),
+                (Line::Instruction { .. }, None) => "#####".to_owned(),
+                (Line::Data { .. }, None) => "-".to_owned(),
+            };
+            write!(report, "{:>8} {}", count, line).unwrap();
+            if let Some(counts) = self.branches.get(&pos) {
+                write!(report, "    ; taken {}, not taken {}", counts.taken, counts.not_taken).unwrap();
+            }
+            report.push('\n');
+        }
+        report
+    }
+
+    /// Report in lcov's tracefile format, treating each address as a line. `name` is used as
+    /// the source file name.
+    pub fn to_lcov(&self, name: &str, memory: &[Integer], extensions: &Extensions) -> String {
+        let instructions: BTreeSet<Position> = self.lines(memory, extensions).iter()
+            .filter(|line| matches!(line, Line::Instruction { .. }))
+            .map(|line| line.pos())
+            .chain(self.hits.keys().cloned())
+            .collect();
+        let mut report = String::new();
+        writeln!(report, "TN:").unwrap();
+        writeln!(report, "SF:{}", name).unwrap();
+        for (&pos, counts) in self.branches.iter() {
+            writeln!(report, "BRDA:{},0,0,{}", pos, counts.taken).unwrap();
+            writeln!(report, "BRDA:{},0,1,{}", pos, counts.not_taken).unwrap();
+        }
+        let branches_hit = self.branches.values()
+            .map(|counts| (counts.taken > 0) as usize + (counts.not_taken > 0) as usize)
+            .sum::<usize>();
+        writeln!(report, "BRF:{}", self.branches.len() * 2).unwrap();
+        writeln!(report, "BRH:{}", branches_hit).unwrap();
+        for &pos in instructions.iter() {
+            writeln!(report, "DA:{},{}", pos, self.hits(pos)).unwrap();
+        }
+        writeln!(report, "LF:{}", instructions.len()).unwrap();
+        writeln!(report, "LH:{}", self.hits.len()).unwrap();
+        writeln!(report, "end_of_record").unwrap();
+        report
+    }
+}
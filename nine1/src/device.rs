@@ -0,0 +1,201 @@
+//! Peripheral devices mapped onto ranges of memory.
+//!
+//! Once a device is attached to an address range, every read from or write to an address in
+//! that range goes to the device instead of to memory. This covers the params of an instruction
+//! as well as the instruction itself, so code can be run straight out of a device.
+//!
+//! Ordering within a cycle:
+//!
+//! 1. Every device is ticked once, in the order they were attached.
+//! 2. The opcode is read.
+//! 3. Params are read from first to last. For a position or relative mode param, the param
+//!    cell itself is read before the cell it refers to. The address for the output param is
+//!    resolved at the point where that param comes up, but nothing is written yet.
+//! 4. For Input, the input function is called.
+//! 5. The result, if any, is written.
+//!
+//! So a device never sees a write from an instruction before all of that instruction's reads,
+//! and a clock read during cycle n (counting from 1) returns n.
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::{Integer, IntcodeError, Position, MEMORY_SIZE};
+
+pub trait Device {
+    /// Value read from the given offset into the device's address range
+    fn read(&mut self, offset: Position) -> Integer;
+    /// Value written to the given offset into the device's address range
+    fn write(&mut self, offset: Position, val: Integer);
+    /// Called once at the start of every cycle
+    fn tick(&mut self) {}
+    /// Most cells the device can be mapped onto, or None if it answers at any offset
+    fn capacity(&self) -> Option<Position> {
+        None
+    }
+}
+
+type SharedDevice = Rc<RefCell<dyn Device>>;
+
+/// Devices attached to a program, by address range. Clones share the same devices, like clones of
+/// a `Framebuffer` share its pixels, so a cloned `Machine` sees the state its original left behind.
+#[derive(Clone, Default)]
+pub struct Devices {
+    mapped: Vec<(Range<Position>, SharedDevice)>,
+}
+
+impl Devices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the device onto the address range. Ranges of different devices may not overlap, and
+    /// the range may not be bigger than the device's capacity or go past the end of memory.
+    pub fn attach<D: Device + 'static>(&mut self, range: Range<Position>, device: D) -> Result<(), IntcodeError> {
+        let overlaps = self.mapped.iter()
+            .any(|(other, _)| range.start < other.end && other.start < range.end);
+        let too_big = device.capacity().is_some_and(|capacity| range.end - range.start > capacity);
+        if range.start >= range.end || range.end > MEMORY_SIZE as Position || overlaps || too_big {
+            return Err(IntcodeError::InvalidDeviceRange { start: range.start, end: range.end });
+        }
+        self.mapped.push((range, Rc::new(RefCell::new(device))));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mapped.is_empty()
+    }
+
+    fn find(&self, pos: Position) -> Option<(Position, &SharedDevice)> {
+        self.mapped.iter()
+            .find(|(range, _)| range.contains(&pos))
+            .map(|(range, device)| (pos - range.start, device))
+    }
+
+    /// Value from the device mapped at pos, if there is one
+    pub(crate) fn read(&self, pos: Position) -> Option<Integer> {
+        self.find(pos).map(|(offset, device)| device.borrow_mut().read(offset))
+    }
+
+    /// Write to the device mapped at pos, returning false if there isn't one
+    pub(crate) fn write(&self, pos: Position, val: Integer) -> bool {
+        match self.find(pos) {
+            Some((offset, device)) => {
+                device.borrow_mut().write(offset, val);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn tick(&self) {
+        for (_, device) in self.mapped.iter() {
+            device.borrow_mut().tick();
+        }
+    }
+}
+
+/// Counts cycles. Reads give the current count, writes set it.
+#[derive(Debug, Default)]
+pub struct Clock {
+    count: Integer,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Device for Clock {
+    fn read(&mut self, _offset: Position) -> Integer {
+        self.count
+    }
+
+    fn write(&mut self, _offset: Position, val: Integer) {
+        self.count = val;
+    }
+
+    fn tick(&mut self) {
+        self.count += 1;
+    }
+}
+
+/// Pseudo-random numbers from a fixed seed, so runs are repeatable. Every read gives the next
+/// non-negative number, writes reseed the generator.
+#[derive(Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+//        xorshift gets stuck on a state of 0
+        Self { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+}
+
+impl Device for Random {
+    fn read(&mut self, _offset: Position) -> Integer {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 1) as Integer
+    }
+
+    fn write(&mut self, _offset: Position, val: Integer) {
+        *self = Self::new(val as u64);
+    }
+}
+
+/// Grid of pixels, one cell per pixel in rows from the top left. Clones share the same pixels,
+/// so keep a clone around to look at the picture after attaching one to a program.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pixels: Rc<RefCell<Vec<Integer>>>,
+    width: usize,
+    height: usize,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            pixels: Rc::new(RefCell::new(vec![0; width * height])),
+            width,
+            height,
+        }
+    }
+
+    /// Number of cells to map the framebuffer onto
+    pub fn size(&self) -> Position {
+        (self.width * self.height) as Position
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Integer {
+        self.pixels.borrow()[y * self.width + x]
+    }
+
+    /// Picture with a block for every non-zero pixel
+    pub fn render(&self) -> String {
+        let pixels = self.pixels.borrow();
+        pixels.chunks(self.width)
+            .take(self.height)
+            .map(|row| row.iter().map(|&px| if px != 0 { "█" } else { " " }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: Position) -> Integer {
+        self.pixels.borrow()[offset as usize]
+    }
+
+    fn write(&mut self, offset: Position, val: Integer) {
+        self.pixels.borrow_mut()[offset as usize] = val;
+    }
+
+    fn capacity(&self) -> Option<Position> {
+        Some(self.size())
+    }
+}
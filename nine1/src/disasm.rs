@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::{read_opcode, Extensions, Instruction, Integer, Opcode, ParamMode, Position};
+
+/// A line of disassembly: either a decoded instruction or a value that couldn't be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Instruction {
+        pos: Position,
+        name: String,
+        params: Vec<(ParamMode, Integer)>,
+    },
+    Data {
+        pos: Position,
+        value: Integer,
+    },
+}
+
+impl Line {
+    pub fn pos(&self) -> Position {
+        match self {
+            Line::Instruction { pos, .. } | Line::Data { pos, .. } => *pos,
+        }
+    }
+
+    /// Number of memory cells covered by the line
+    pub fn size(&self) -> usize {
+        match self {
+            Line::Instruction { params, .. } => 1 + params.len(),
+            Line::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Instruction { pos, name, params } => {
+                write!(f, "{:>6}: {:<18}", pos, name)?;
+                let params = params.iter().map(|(mode, val)| match mode {
+                    ParamMode::POSITION => format!("[{}]", val),
+                    ParamMode::IMMEDIATE => format!("{}", val),
+                    ParamMode::RELATIVE => format!("[rb{:+}]", val),
+                }).collect::<Vec<_>>();
+                write!(f, "{}", params.join(", "))
+            }
+            Line::Data { pos, value } => write!(f, "{:>6}: {:<18}{}", pos, "data", value),
+        }
+    }
+}
+
+/// Line starting at pos, which is data if there's no valid instruction there
+pub(crate) fn decode_line(memory: &[Integer], pos: usize, extensions: &Extensions) -> Line {
+    let value = memory[pos];
+    let decoded = read_opcode(pos as Position, value, extensions).ok().map(|(opcode, modes)| {
+        match opcode {
+            Opcode::Builtin(instruction) => (format!("{:?}", instruction), instruction.num_params(), modes),
+            Opcode::Extension(code) => {
+                let extension = extensions.get(code).unwrap();
+                (extension.name().to_owned(), extension.num_params(), modes)
+            }
+        }
+    });
+    match decoded {
+        Some((name, num_params, modes)) if pos + num_params < memory.len() => Line::Instruction {
+            pos: pos as Position,
+            name,
+            params: (0..num_params).map(|i| (modes[i], memory[pos + 1 + i])).collect(),
+        },
+        _ => Line::Data { pos: pos as Position, value },
+    }
+}
+
+/// Linear sweep over memory, decoding instructions (including registered extensions) where
+/// possible and falling back to data for anything else
+pub fn disassemble(memory: &[Integer], extensions: &Extensions) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    while pos < memory.len() {
+        let line = decode_line(memory, pos, extensions);
+        pos += line.size();
+        lines.push(line);
+    }
+    lines
+}
+
+/// Builtin instruction found by `disassemble`, for passes which work on decoded code
+pub(crate) struct Decoded {
+    pub line: Line,
+    pub instruction: Instruction,
+    pub params: Vec<(ParamMode, Integer)>,
+}
+
+impl Decoded {
+    pub fn pos(&self) -> Position {
+        self.line.pos()
+    }
+
+    /// Position of the following instruction
+    pub fn next(&self) -> Position {
+        self.pos() + self.line.size() as Position
+    }
+}
+
+/// Builtin instructions in memory, as found by a linear sweep
+pub(crate) fn decode_builtins(memory: &[Integer]) -> Vec<Decoded> {
+    let extensions = Extensions::new();
+    disassemble(memory, &extensions).into_iter().filter_map(|line| match &line {
+        Line::Instruction { pos, params, .. } => match read_opcode(*pos, memory[*pos as usize], &extensions) {
+            Ok((Opcode::Builtin(instruction), _)) => Some(Decoded { instruction, params: params.clone(), line }),
+            _ => None,
+        },
+        Line::Data { .. } => None,
+    }).collect()
+}
@@ -0,0 +1,244 @@
+//! Repair droid for programs which take movement commands (1 north, 2 south, 3 west, 4 east) and
+//! answer each with a status: 0 if the droid hit a wall and stayed put, 1 if it moved, and 2 if it
+//! moved and found the target.
+//!
+//! Rather than walking the droid back and forth, the maze is explored breadth first by cloning the
+//! paused `Machine` at every cell reached, so each clone only ever has to try the next step. The
+//! droid starts at `(0, 0)`, with north going towards negative y.
+//!
+//! ```
+//! use nine1::{compiler, droid::{self, Cell, Move}};
+//!
+//! // Corridor going east from the start, with the target at the end of it
+//! let maze = droid::explore(&compiler::compile("
+//!     let x = 0;
+//!     while 1 {
+//!         if input() == 4 && x < 2 {
+//!             x = x + 1;
+//!             output(1 + (x == 2));
+//!         } else {
+//!             output(0);
+//!         }
+//!     }
+//! ").unwrap()).unwrap();
+//! assert_eq!(maze.target(), Some((2, 0)));
+//! assert_eq!(maze.path(), Some(vec![Move::East, Move::East]));
+//! assert_eq!(maze.cell((1, -1)), Some(Cell::Wall));
+//! assert_eq!(maze.render(), " ███ \n█D.O█\n ███ ");
+//! ```
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use crate::loader::{self, LoadError};
+use crate::{Integer, IntcodeError, Machine, State};
+
+pub type Point = (Integer, Integer);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    North = 1,
+    South = 2,
+    West = 3,
+    East = 4,
+}
+
+impl Move {
+    pub const ALL: [Move; 4] = [Move::North, Move::South, Move::West, Move::East];
+
+    /// Where the move goes from pos
+    pub fn from(self, (x, y): Point) -> Point {
+        match self {
+            Move::North => (x, y - 1),
+            Move::South => (x, y + 1),
+            Move::West => (x - 1, y),
+            Move::East => (x + 1, y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Wall,
+    Open,
+    Target,
+}
+
+#[derive(Debug)]
+pub enum DroidError {
+    /// The program's intcode couldn't be parsed
+    Load(LoadError),
+    Intcode(IntcodeError),
+    /// Program halted or asked for another command instead of answering a move
+    NoStatus { from: Point, step: Move },
+    /// Program answered a move with something other than 0, 1 or 2
+    InvalidStatus { from: Point, step: Move, status: Integer },
+}
+
+impl fmt::Display for DroidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DroidError::Load(err) => write!(f, "{}", err),
+            DroidError::Intcode(err) => write!(f, "{}", err),
+            DroidError::NoStatus { from, step } =>
+                write!(f, "No status for moving {:?} from {:?}", step, from),
+            DroidError::InvalidStatus { from, step, status } =>
+                write!(f, "Invalid status {} for moving {:?} from {:?}", status, step, from),
+        }
+    }
+}
+
+impl Error for DroidError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DroidError::Load(err) => Some(err),
+            DroidError::Intcode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadError> for DroidError {
+    fn from(err: LoadError) -> Self {
+        DroidError::Load(err)
+    }
+}
+
+impl From<IntcodeError> for DroidError {
+    fn from(err: IntcodeError) -> Self {
+        DroidError::Intcode(err)
+    }
+}
+
+/// Everything the droid could reach, and the walls around it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Maze {
+    cells: BTreeMap<Point, Cell>,
+    target: Option<Point>,
+    /// Move which first reached each open cell, and the cell it was made from
+    came_from: BTreeMap<Point, (Point, Move)>,
+}
+
+impl Maze {
+    /// What's at pos, or None if the droid never found out
+    pub fn cell(&self, pos: Point) -> Option<Cell> {
+        self.cells.get(&pos).cloned()
+    }
+
+    pub fn cells(&self) -> &BTreeMap<Point, Cell> {
+        &self.cells
+    }
+
+    pub fn target(&self) -> Option<Point> {
+        self.target
+    }
+
+    /// Shortest path from the start to pos, if the droid reached it
+    pub fn path_to(&self, mut pos: Point) -> Option<Vec<Move>> {
+        let mut path = Vec::new();
+        while pos != (0, 0) {
+            let &(from, step) = self.came_from.get(&pos)?;
+            path.push(step);
+            pos = from;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Shortest path from the start to the target, if there is one
+    pub fn path(&self) -> Option<Vec<Move>> {
+        self.path_to(self.target?)
+    }
+
+    /// Number of steps from pos to each cell that isn't a wall, i.e. the minute at which something
+    /// flooding out from pos reaches each cell
+    pub fn fill_times(&self, pos: Point) -> BTreeMap<Point, usize> {
+        let mut times = BTreeMap::new();
+        if matches!(self.cell(pos), None | Some(Cell::Wall)) {
+            return times;
+        }
+        times.insert(pos, 0);
+        let mut queue = VecDeque::from(vec![pos]);
+        while let Some(pos) = queue.pop_front() {
+            let time = times[&pos] + 1;
+            for step in Move::ALL.iter() {
+                let next = step.from(pos);
+                if matches!(self.cell(next), Some(Cell::Open) | Some(Cell::Target)) && !times.contains_key(&next) {
+                    times.insert(next, time);
+                    queue.push_back(next);
+                }
+            }
+        }
+        times
+    }
+
+    /// Minutes it takes to fill the maze starting from the target
+    pub fn fill_time(&self) -> Option<usize> {
+        self.fill_times(self.target?).values().max().cloned()
+    }
+
+    /// Map of the maze, with walls as █, open cells as `.`, the target as `O` and the start as `D`
+    pub fn render(&self) -> String {
+        let min_x = self.cells.keys().map(|&(x, _)| x).min().unwrap_or(0);
+        let max_x = self.cells.keys().map(|&(x, _)| x).max().unwrap_or(0);
+        let min_y = self.cells.keys().map(|&(_, y)| y).min().unwrap_or(0);
+        let max_y = self.cells.keys().map(|&(_, y)| y).max().unwrap_or(0);
+        (min_y..=max_y)
+            .map(|y| (min_x..=max_x).map(|x| match self.cell((x, y)) {
+                Some(Cell::Target) => 'O',
+                _ if (x, y) == (0, 0) => 'D',
+                Some(Cell::Wall) => '█',
+                Some(Cell::Open) => '.',
+                None => ' ',
+            }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Explore the whole maze the program describes
+pub fn explore(intcode: &str) -> Result<Maze, DroidError> {
+    explore_from(Machine::new(loader::parse(intcode)?))
+}
+
+/// Explore the whole maze, starting from wherever the machine has got to, e.g. one set up with
+/// extensions or a cycle limit
+pub fn explore_from(machine: Machine) -> Result<Maze, DroidError> {
+    let mut maze = Maze::default();
+    maze.cells.insert((0, 0), Cell::Open);
+    let mut frontier = VecDeque::from(vec![((0, 0), machine)]);
+    while let Some((pos, machine)) = frontier.pop_front() {
+//        A droid which hit a wall is still at pos, so it can try the next step instead of a new clone
+        let mut idle: Option<Machine> = None;
+        for &step in Move::ALL.iter() {
+            let next = step.from(pos);
+            if maze.cells.contains_key(&next) {
+                continue;
+            }
+            let mut droid = idle.take().unwrap_or_else(|| machine.clone());
+            droid.push_input(step as Integer);
+            let status = match droid.resume()? {
+                State::Output(status) => status,
+                State::NeedsInput | State::Halted => return Err(DroidError::NoStatus { from: pos, step }),
+            };
+            let cell = match status {
+                0 => Cell::Wall,
+                1 => Cell::Open,
+                2 => Cell::Target,
+                _ => return Err(DroidError::InvalidStatus { from: pos, step, status }),
+            };
+            log::debug!("Moving {:?} from {:?} found {:?}", step, pos, cell);
+            maze.cells.insert(next, cell);
+            if cell == Cell::Wall {
+                idle = Some(droid);
+                continue;
+            }
+            if cell == Cell::Target {
+                maze.target = Some(next);
+            }
+            maze.came_from.insert(next, (pos, step));
+            frontier.push_back((next, droid));
+        }
+    }
+    Ok(maze)
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Integer, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntcodeError {
+    /// Last 2 digits of the opcode are neither a built-in instruction nor a registered extension
+    UnknownOpcode { pos: Position, opcode: Integer },
+    /// One of the mode digits of the opcode is not a known parameter mode
+    InvalidParamMode { pos: Position, opcode: Integer, mode: Integer },
+    /// A parameter that is written to was given in IMMEDIATE mode
+    ImmediateWrite { pos: Position },
+    /// The input function had no more values to give to an Input instruction
+    InputExhausted { pos: Position },
+    /// The program ran for longer than the cycle limit it was given
+    CycleLimit { pos: Position, cycles: u64 },
+    /// A parameter resolved to an address below 0, or a jump went to one (then `pos` is the jump's)
+    NegativeAddress { pos: Position, address: Integer },
+    /// An address, or the instruction pointer, went past the end of memory
+    AddressOutOfRange { pos: Position, address: Integer },
+    /// Arithmetic on the instruction's params, or on the relative base, overflowed
+    Overflow { pos: Position },
+    /// An extension asked to store a result but wasn't registered with an output parameter
+    MissingOutputParam { pos: Position, opcode: u8 },
+    /// Device address range is empty, overlaps with another device or is bigger than the device
+    InvalidDeviceRange { start: Position, end: Position },
+    /// Extension opcodes have to fit in the last 2 digits of an opcode
+    OpcodeOutOfRange(u8),
+    /// Opcode is already taken by a built-in instruction or another extension
+    OpcodeInUse(u8),
+    /// There are only mode digits for up to 3 parameters
+    TooManyParams { opcode: u8, num_params: usize },
+    /// Extension with an output has to have a param to write it to
+    NoOutputParam(u8),
+}
+
+impl IntcodeError {
+    /// Position of the instruction or param the error happened at, if it happened while running
+    pub fn pos(&self) -> Option<Position> {
+        use IntcodeError::*;
+        match self {
+            UnknownOpcode { pos, .. } | InvalidParamMode { pos, .. } | ImmediateWrite { pos }
+            | InputExhausted { pos } | CycleLimit { pos, .. } | NegativeAddress { pos, .. }
+            | AddressOutOfRange { pos, .. } | Overflow { pos } | MissingOutputParam { pos, .. } => Some(*pos),
+            InvalidDeviceRange { .. } | OpcodeOutOfRange(_) | OpcodeInUse(_) | TooManyParams { .. }
+            | NoOutputParam(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use IntcodeError::*;
+        match self {
+            UnknownOpcode { pos, opcode } =>
+                write!(f, "Unknown opcode {} at pos {}", opcode, pos),
+            InvalidParamMode { pos, opcode, mode } =>
+                write!(f, "Invalid param mode {} in opcode {} at pos {}", mode, opcode, pos),
+            ImmediateWrite { pos } =>
+                write!(f, "Output param at pos {} uses IMMEDIATE param mode", pos),
+            InputExhausted { pos } =>
+                write!(f, "Ran out of input for instruction at pos {}", pos),
+            CycleLimit { pos, cycles } =>
+                write!(f, "Reached cycle limit of {} at pos {}", cycles, pos),
+            NegativeAddress { pos, address } =>
+                write!(f, "Param at pos {} refers to negative address {}", pos, address),
+            AddressOutOfRange { pos, address } =>
+                write!(f, "Address {} used at pos {} is past the end of memory", address, pos),
+            Overflow { pos } =>
+                write!(f, "Arithmetic overflowed at pos {}", pos),
+            MissingOutputParam { pos, opcode } =>
+                write!(f, "Extension {} at pos {} has no output param to store to", opcode, pos),
+            InvalidDeviceRange { start, end } =>
+                write!(f, "Can't attach device to addresses {}..{}", start, end),
+            OpcodeOutOfRange(opcode) =>
+                write!(f, "Extension opcode {} doesn't fit in 2 digits", opcode),
+            OpcodeInUse(opcode) =>
+                write!(f, "Opcode {} is already in use", opcode),
+            TooManyParams { opcode, num_params } =>
+                write!(f, "Extension {} has {} params but at most 3 are supported", opcode, num_params),
+            NoOutputParam(opcode) =>
+                write!(f, "Extension {} has an output but no params to write it to", opcode),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
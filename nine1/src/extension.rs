@@ -0,0 +1,131 @@
+//! Custom opcodes on top of the built-in instruction set.
+//!
+//! An extension is registered under an unused 2-digit opcode together with its number of
+//! parameters. Parameters are read using the usual param modes and passed to the handler,
+//! which decides what happens next by returning an `ExtensionAction`.
+//!
+//! A host syscall which takes a syscall number and an argument and stores the result:
+//!
+//! ```
+//! use nine1::{Extension, ExtensionAction, Extensions};
+//!
+//! let mut extensions = Extensions::new();
+//! extensions.register(42, Extension::new("syscall", 3, |params| {
+//!     match params[0] {
+//!         0 => ExtensionAction::Store(params[1] * 2),
+//!         _ => ExtensionAction::Halt(-1),
+//!     }
+//! }).with_output()).unwrap();
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::{Instruction, Integer, IntcodeError, NUM_PARAMS};
+
+/// What the VM should do after an extension has run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtensionAction {
+    /// Move on to the next instruction
+    Continue,
+    /// Write the value to the output param, then move on to the next instruction
+    Store(Integer),
+    /// Continue execution at the given position
+    Jump(Integer),
+    /// Stop execution, recording the given exit code
+    Halt(Integer),
+}
+
+/// Shared so that machines with the same extensions can be cloned cheaply
+type Handler = Rc<dyn Fn(&[Integer]) -> ExtensionAction>;
+
+#[derive(Clone)]
+pub struct Extension {
+    name: String,
+    num_params: usize,
+    has_output: bool,
+    handler: Handler,
+}
+
+impl Extension {
+    pub fn new<F>(name: &str, num_params: usize, handler: F) -> Self
+        where F: Fn(&[Integer]) -> ExtensionAction + 'static
+    {
+        Self {
+            name: name.to_owned(),
+            num_params,
+            has_output: false,
+            handler: Rc::new(handler),
+        }
+    }
+
+    /// Treat the last param as an address to write `ExtensionAction::Store` values to.
+    /// The handler then only gets passed the params before it, and the extension can't be
+    /// registered without any params.
+    pub fn with_output(mut self) -> Self {
+        self.has_output = true;
+        self
+    }
+
+    /// Prints its single param to stderr
+    pub fn debug_print() -> Self {
+        Self::new("debug", 1, |params| {
+            eprintln!("Debug: {}", params[0]);
+            ExtensionAction::Continue
+        })
+    }
+
+    /// Halts the program with its single param as the exit code
+    pub fn halt_with_code() -> Self {
+        Self::new("halt", 1, |params| ExtensionAction::Halt(params[0]))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_params(&self) -> usize {
+        self.num_params
+    }
+
+    pub fn has_output(&self) -> bool {
+        self.has_output
+    }
+
+    pub(crate) fn call(&self, params: &[Integer]) -> ExtensionAction {
+        (self.handler)(params)
+    }
+}
+
+/// Registry of extensions by opcode
+#[derive(Clone, Default)]
+pub struct Extensions {
+    by_opcode: HashMap<u8, Extension>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, opcode: u8, extension: Extension) -> Result<(), IntcodeError> {
+        if opcode >= 100 {
+            return Err(IntcodeError::OpcodeOutOfRange(opcode));
+        }
+        if extension.num_params > NUM_PARAMS {
+            return Err(IntcodeError::TooManyParams { opcode, num_params: extension.num_params });
+        }
+        if extension.has_output && extension.num_params == 0 {
+            return Err(IntcodeError::NoOutputParam(opcode));
+        }
+        if Instruction::try_from(opcode).is_ok() || self.by_opcode.contains_key(&opcode) {
+            return Err(IntcodeError::OpcodeInUse(opcode));
+        }
+        self.by_opcode.insert(opcode, extension);
+        Ok(())
+    }
+
+    pub fn get(&self, opcode: u8) -> Option<&Extension> {
+        self.by_opcode.get(&opcode)
+    }
+}
@@ -0,0 +1,257 @@
+//! Compact binary format for programs, which is much smaller and quicker to load than intcode
+//! text for large generated programs.
+//!
+//! An image is laid out as:
+//!
+//! ```text
+//! magic     4 bytes  "\0INT"
+//! version   1 byte   1
+//! width     1 byte   number of bits values were written for, at most 64
+//! flags     1 byte   bit 0 set if there's a symbol table
+//! length    varint   number of values
+//! values    varints  each value zigzag encoded
+//! symbols            if flagged: varint count, then for each a varint address, varint name
+//!                    length and the UTF-8 name
+//! checksum  4 bytes  CRC-32 of everything before it, little endian
+//! ```
+//!
+//! Varints are LEB128: 7 bits at a time, least significant first, with the top bit set on every
+//! byte but the last. Zigzag encoding maps 0, -1, 1, -2, ... to 0, 1, 2, 3, ... so small negative
+//! values stay small.
+//!
+//! ```
+//! use nine1::image::Image;
+//!
+//! let image = Image::new(vec![104, -1, 99]).with_symbol(0, "main");
+//! let bytes = image.to_bytes();
+//! assert_eq!(bytes.len(), 24);
+//! assert_eq!(Image::from_bytes(&bytes).unwrap(), image);
+//! ```
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{Integer, LoadError, Position};
+
+/// First bytes of every image. Starting with a NUL means it can't be mistaken for intcode text.
+pub const MAGIC: &[u8; 4] = b"\0INT";
+pub const VERSION: u8 = 1;
+const HAS_SYMBOLS: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// Number of bits the values fit in, e.g. 32 for a program meant for a VM with i32 values
+    pub width: u8,
+    pub memory: Vec<Integer>,
+    /// Names of addresses, e.g. where functions start
+    pub symbols: BTreeMap<Position, String>,
+}
+
+/// Whether the bytes look like an image rather than intcode text
+pub fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut val: u64) {
+    while val >= 0x80 {
+        bytes.push((val as u8 & 0x7f) | 0x80);
+        val >>= 7;
+    }
+    bytes.push(val as u8);
+}
+
+fn zigzag(val: Integer) -> u64 {
+    ((val << 1) ^ (val >> 63)) as u64
+}
+
+fn unzigzag(val: u64) -> Integer {
+    ((val >> 1) as Integer) ^ -((val & 1) as Integer)
+}
+
+fn invalid<T>(reason: String) -> Result<T, LoadError> {
+    Err(LoadError::InvalidImage(reason))
+}
+
+/// Position in the bytes of an image being read
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize, what: &str) -> Result<&'a [u8], LoadError> {
+        match self.bytes.get(self.pos..self.pos + len) {
+            Some(taken) => {
+                self.pos += len;
+                Ok(taken)
+            }
+            None => invalid(format!("Image ends in the middle of the {}", what)),
+        }
+    }
+
+    fn byte(&mut self, what: &str) -> Result<u8, LoadError> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn varint(&mut self, what: &str) -> Result<u64, LoadError> {
+        let start = self.pos;
+        let mut val = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte(what)?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            val |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+        invalid(format!("Varint for the {} at byte {} is too long", what, start))
+    }
+
+    fn size(&mut self, what: &str) -> Result<usize, LoadError> {
+        let start = self.pos;
+        let size = self.varint(what)?;
+//        Every value and character takes at least a byte, so this also stops a corrupt size
+//        from allocating far more memory than the image could fill
+        if size > (self.bytes.len() - self.pos) as u64 {
+            return invalid(format!("The {} at byte {} is larger than the image", what, start));
+        }
+        Ok(size as usize)
+    }
+}
+
+impl Image {
+    /// Image of 64-bit values with no symbols
+    pub fn new(memory: Vec<Integer>) -> Self {
+        Self { width: 64, memory, symbols: BTreeMap::new() }
+    }
+
+    /// Record that the values fit in a narrower integer type
+    pub fn with_width(mut self, width: u8) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_symbol(mut self, pos: Position, name: &str) -> Self {
+        self.symbols.insert(pos, name.to_owned());
+        self
+    }
+
+    fn fits(&self, val: Integer) -> bool {
+        self.width >= 64 || (val >= -(1 << (self.width - 1)) && val < 1 << (self.width - 1))
+    }
+
+    /// Encoded image. Panics if the width isn't from 1 to 64 bits or a value doesn't fit in it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        assert!((1..=64).contains(&self.width), "Invalid image width {}", self.width);
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.width);
+        bytes.push(if self.symbols.is_empty() { 0 } else { HAS_SYMBOLS });
+        write_varint(&mut bytes, self.memory.len() as u64);
+        for (index, &val) in self.memory.iter().enumerate() {
+            assert!(self.fits(val), "Value {} at index {} doesn't fit in {} bits", val, index, self.width);
+            write_varint(&mut bytes, zigzag(val));
+        }
+        if !self.symbols.is_empty() {
+            write_varint(&mut bytes, self.symbols.len() as u64);
+            for (&pos, name) in self.symbols.iter() {
+                write_varint(&mut bytes, pos);
+                write_varint(&mut bytes, name.len() as u64);
+                bytes.extend_from_slice(name.as_bytes());
+            }
+        }
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LoadError> {
+        if !is_image(bytes) {
+            return invalid("Image doesn't start with the image header".to_owned());
+        }
+        if bytes.len() < MAGIC.len() + 4 {
+            return invalid("Image ends in the middle of the header".to_owned());
+        }
+        let (contents, checksum) = bytes.split_at(bytes.len() - 4);
+        let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+        let actual = crc32(contents);
+        if expected != actual {
+            return Err(LoadError::ChecksumMismatch { expected, actual });
+        }
+
+        let mut cursor = Cursor { bytes: contents, pos: MAGIC.len() };
+        let version = cursor.byte("header")?;
+        if version != VERSION {
+            return invalid(format!("Image version {} isn't supported", version));
+        }
+        let width = cursor.byte("header")?;
+        if !(1..=64).contains(&width) {
+            return invalid(format!("Image width {} isn't supported", width));
+        }
+        let flags = cursor.byte("header")?;
+        if flags & !HAS_SYMBOLS != 0 {
+            return invalid(format!("Image has unknown flags {:#x}", flags));
+        }
+
+        let mut image = Image::new(Vec::new()).with_width(width);
+        let len = cursor.size("length")?;
+        image.memory.reserve(len);
+        for index in 0..len {
+            let val = unzigzag(cursor.varint("values")?);
+            if !image.fits(val) {
+                return invalid(format!("Value {} at index {} doesn't fit in {} bits", val, index, width));
+            }
+            image.memory.push(val);
+        }
+        if flags & HAS_SYMBOLS != 0 {
+            let count = cursor.size("symbol count")?;
+            for _ in 0..count {
+                let pos = cursor.varint("symbol table")?;
+                let name_len = cursor.size("symbol name length")?;
+                let name = cursor.take(name_len, "symbol table")?;
+                let name = String::from_utf8(name.to_vec())
+                    .or_else(|_| invalid(format!("Name of symbol at {} isn't UTF-8", pos)))?;
+                image.symbols.insert(pos, name);
+            }
+        }
+        if cursor.pos != contents.len() {
+            return invalid(format!("Image has {} unexpected bytes at the end", contents.len() - cursor.pos));
+        }
+        Ok(image)
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, LoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
@@ -1,35 +1,74 @@
 use num_enum::TryFromPrimitive;
-use std::convert::{TryFrom, TryInto};
-use log;
+use std::convert::TryFrom;
+use std::io::Read;
+use std::path::Path;
 
-type Position = u64;
+pub mod aot;
+pub mod arcade;
+pub mod ascii;
+pub mod compiler;
+pub mod device;
+pub mod droid;
+pub mod image;
+pub mod lint;
+pub mod loader;
+pub mod optimizer;
+pub mod robot;
+pub mod scheduler;
+mod coverage;
+mod disasm;
+mod error;
+mod extension;
+mod machine;
+mod memory;
+mod report;
+mod memdiff;
+mod session;
+
+pub use disasm::{disassemble, Line};
+pub use coverage::{BranchCounts, Coverage};
+pub use device::{Device, Devices};
+pub use error::IntcodeError;
+pub use extension::{Extension, ExtensionAction, Extensions};
+pub use loader::LoadError;
+pub use machine::{Machine, Outputs, State};
+pub use memory::{Memory, PAGE_SIZE};
+pub use report::{HaltReason, RunReport, Stats};
+pub use memdiff::{code_addresses, diff_memory, ChangedRange, MemoryDiff, Region};
+pub use session::{replay, Divergence, Event, Session};
+
+pub type Position = u64;
 pub type Integer = i64;
 
+/// Number of memory cells a program gets
+pub(crate) const MEMORY_SIZE: usize = 10000000;
+
+/// Machine with functions to get its input from and send its output to
 pub struct Program<I, O>
-    where I: Fn() -> Integer, O: Fn(Integer)
+    where I: Fn() -> Option<Integer>, O: Fn(Integer)
 {
-    data: Vec<Integer>,
+    machine: Machine,
     input_fn: I,
     output_fn: O,
-    relative_base: Integer,
 }
 
 
-#[derive(Debug, TryFromPrimitive)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
 #[repr(u8)]
-enum ParamMode {
+pub enum ParamMode {
     POSITION = 0,
     IMMEDIATE = 1,
     RELATIVE = 2,
 }
 
-const NUM_PARAMS: usize = 3;
+pub const NUM_PARAMS: usize = 3;
 
-type ParamModes = [ParamMode; NUM_PARAMS];
+pub type ParamModes = [ParamMode; NUM_PARAMS];
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromPrimitive)]
 #[repr(u8)]
-enum Instruction {
+pub enum Instruction {
     Add = 1,
     Multiply = 2,
     Input = 3,
@@ -42,160 +81,228 @@ enum Instruction {
     Halt = 99,
 }
 
-fn read_opcode(param: Integer) -> (Instruction, ParamModes) {
-    let a = ((param / 10000) % 1000) as u8;
-    let b = ((param - (a as i64 * 10000)) / 1000) as u8;
-    let c = ((param - (a as i64 * 10000) - (b as i64 * 1000)) / 100) as u8;
-//        TODO: Remove ugly use of unwrap. Should use ? to return Result's instead?
+impl Instruction {
+    /// Number of params following the opcode
+    pub fn num_params(&self) -> usize {
+        use Instruction::*;
+        match self {
+            Add | Multiply | LessThan | Equals => 3,
+            JumpIfTrue | JumpIfFalse => 2,
+            Input | Output | RelativeBaseOffset => 1,
+            Halt => 0,
+        }
+    }
+
+    /// Whether the param with the index is an address the instruction writes to
+    pub fn is_output_param(&self, i: usize) -> bool {
+        use Instruction::*;
+        matches!((self, i), (Add, 2) | (Multiply, 2) | (LessThan, 2) | (Equals, 2) | (Input, 0))
+    }
+}
+
+/// Instruction part of an opcode: either built in or a registered extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    Builtin(Instruction),
+    Extension(u8),
+}
+
+pub fn read_opcode(pos: Position, param: Integer, extensions: &Extensions) -> Result<(Opcode, ParamModes), IntcodeError> {
+    if param < 0 {
+        return Err(IntcodeError::UnknownOpcode { pos, opcode: param });
+    }
     let last_2_digits = (param % 100) as u8;
-    let instruction = Instruction::try_from(last_2_digits).expect(format!("Last 2 digits of opcode {} are not a valid instruction", last_2_digits).as_str());
-    println!("opcode: {}. Modes a {} b {} c {}", param, a, b, c);
-    let modes: ParamModes = [
+    let opcode = match Instruction::try_from(last_2_digits) {
+        Ok(instruction) => Opcode::Builtin(instruction),
+        Err(_) if extensions.get(last_2_digits).is_some() => Opcode::Extension(last_2_digits),
+        Err(_) => return Err(IntcodeError::UnknownOpcode { pos, opcode: param }),
+    };
+    let read_mode = |digit: Integer| {
+        let mode = (param / digit) % 10;
+        ParamMode::try_from(mode as u8)
+            .map_err(|_| IntcodeError::InvalidParamMode { pos, opcode: param, mode })
+    };
 //            Modes are in reverse order of the parameters :)
-        ParamMode::try_from(c).unwrap(),
-        ParamMode::try_from(b).unwrap(),
-        ParamMode::try_from(a).unwrap(),
+    let modes: ParamModes = [
+        read_mode(100)?,
+        read_mode(1000)?,
+        read_mode(10000)?,
     ];
-    (instruction, modes)
+    Ok((opcode, modes))
 }
 
 impl<I, O> Program<I, O>
-    where I: Fn() -> Integer, O: Fn(Integer)
+    where I: Fn() -> Option<Integer>, O: Fn(Integer)
 {
+    /// Program from intcode text, which panics if the text isn't valid. Use `load` or `read_from`
+    /// for text that comes from outside of the code.
     pub fn new(intcode: &str, input_fn: I, output_fn: O) -> Self {
-        let mut memory: Vec<Integer> = intcode.split(",").map(
-            |x: &str| x.parse::<Integer>().unwrap()
-        ).collect();
-        memory.resize(10000000, 0);
-        Self {
-            data: memory,
-            input_fn,
-            output_fn,
-            relative_base: 0
-        }
+        let memory = loader::parse(intcode).unwrap_or_else(|err| panic!("Invalid intcode: {}", err));
+        Self::from_memory(memory, input_fn, output_fn)
     }
-    /// Get value stored at position
-    fn get(&self, pos: Position) -> Integer {
-//        println!("\t\tRead from pos {}", pos);
-        let val = self.data[pos as usize];
-//        println!("\t\tGot {}", val);
-        val
-    }
-    fn read_param(&self, pos: Position, mode: &ParamMode) -> Integer {
-        let param_val = self.get(pos);
-        match mode {
-            ParamMode::POSITION => self.get(param_val.try_into().unwrap()),
-            ParamMode::IMMEDIATE => param_val,
-            ParamMode::RELATIVE => self.get((param_val + self.relative_base).try_into().unwrap()),
-        }
+
+    /// Program from the intcode text or image file at path
+    pub fn load<P: AsRef<Path>>(path: P, input_fn: I, output_fn: O) -> Result<Self, LoadError> {
+        Ok(Self::from_memory(loader::load(path)?, input_fn, output_fn))
     }
 
-    fn read_output_param(&self, pos: Position, mode: &ParamMode) -> Position {
-        let param_val = self.get(pos);
-        let val = match mode {
-            ParamMode::IMMEDIATE => panic!("Shouldn't pass param to Input opcode using IMMEDIATE param mode"),
-            ParamMode::POSITION => param_val,
-            ParamMode::RELATIVE => (param_val + self.relative_base)
-        };
-        val.try_into().unwrap()
+    /// Program from anything which gives intcode text or a binary image
+    pub fn read_from<R: Read>(reader: R, input_fn: I, output_fn: O) -> Result<Self, LoadError> {
+        Ok(Self::from_memory(loader::read_from(reader)?, input_fn, output_fn))
+    }
+
+    /// Program starting with the given memory
+    pub fn from_memory(memory: Vec<Integer>, input_fn: I, output_fn: O) -> Self {
+        Self::from_machine(Machine::new(memory), input_fn, output_fn)
+    }
+
+    /// Program with the machine's state. `resume` carries on from where the machine is, while
+    /// `execute` starts it again from address 0 with the memory it has now.
+    pub fn from_machine(machine: Machine, input_fn: I, output_fn: O) -> Self {
+        Self { machine, input_fn, output_fn }
+    }
+
+    /// State of the program without its IO, which can be cloned and resumed separately
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine {
+        self.machine
+    }
+
+    /// Use the given registry to decode opcodes which aren't built in
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.machine = self.machine.with_extensions(extensions);
+        self
+    }
+
+    /// Map devices onto memory, see the `device` module for details
+    pub fn with_devices(mut self, devices: Devices) -> Self {
+        self.machine = self.machine.with_devices(devices);
+        self
     }
 
-    fn set(&mut self, pos: Position, val: Integer) {
-        let ptr = &mut self.data[pos as usize];
-        *ptr = val;
-        println!("\t\tSet {} to {}", pos, val);
+    /// Record which instructions and branches get executed
+    pub fn with_coverage(mut self) -> Self {
+        self.machine = self.machine.with_coverage();
+        self
     }
 
-    fn read_input(&self) -> Integer {
-        let result = (self.input_fn)();
-        println!("Read input {}", result);
-        result
+    /// Coverage collected so far, if enabled with `with_coverage`
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.machine.coverage()
     }
 
-    fn write_output(&self, val: Integer) {
-        println!("Outputting {}", val);
-        (self.output_fn)(val);
+    /// Record inputs, outputs and halting so the run can be replayed
+    pub fn with_recording(mut self) -> Self {
+        self.machine = self.machine.with_recording();
+        self
     }
 
-    pub fn execute(self: &mut Program<I, O>) -> Option<Integer> {
-        let mut pos = 0;
-        let mut prev_output: Option<Integer> = None;
-        self.relative_base = 0;
+    /// Session recorded so far, if enabled with `with_recording`
+    pub fn session(&self) -> Option<&Session> {
+        self.machine.session()
+    }
+
+    /// Stop with an error instead of running more than the given number of cycles
+    pub fn with_cycle_limit(mut self, cycles: u64) -> Self {
+        self.machine = self.machine.with_cycle_limit(cycles);
+        self
+    }
+
+    /// Exit code given by an extension which halted the program
+    pub fn halt_code(&self) -> Option<Integer> {
+        self.machine.halt_code()
+    }
+
+    /// Number of instructions executed by the last run
+    pub fn cycles(&self) -> u64 {
+        self.machine.cycles()
+    }
+
+    pub fn memory(&self) -> &Memory {
+        self.machine.memory()
+    }
+
+    /// Changes from this program's memory to the other's. Code is told apart from data by the
+    /// coverage of both programs if either recorded any, or else by disassembling this one.
+    pub fn memory_diff<J, P>(&self, other: &Program<J, P>) -> MemoryDiff
+        where J: Fn() -> Option<Integer>, P: Fn(Integer)
+    {
+        self.machine.memory_diff(&other.machine)
+    }
+
+    /// Run from the start until the program halts or stops with an error. Outputs go to the
+    /// output function, and the report says how the run went.
+    pub fn execute(&mut self) -> RunReport {
+        self.machine.restart(0, 0);
+        self.resume()
+    }
+
+    /// Run from where the machine is, keeping its position, relative base and queued input,
+    /// until it halts or stops with an error. The report counts everything since the machine
+    /// was created or last restarted.
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    /// use nine1::{Machine, Program, State};
+    ///
+    /// let mut machine = Machine::new(vec![104, 1, 104, 2, 99]);
+    /// assert_eq!(machine.resume(), Ok(State::Output(1)));
+    /// let outputs = RefCell::new(Vec::new());
+    /// let mut program = Program::from_machine(machine, || None, |val| outputs.borrow_mut().push(val));
+    /// assert_eq!(program.resume().result(), Ok(Some(2)));
+    /// assert_eq!(*outputs.borrow(), vec![2]);
+    /// ```
+    pub fn resume(&mut self) -> RunReport {
+        let halt = match self.run() {
+            Ok(()) => match self.halt_code() {
+                Some(code) => HaltReason::Extension(code),
+                None => HaltReason::Halt,
+            },
+            Err(err) => HaltReason::Error(err),
+        };
+        RunReport { stats: self.machine.stats(), halt }
+    }
+
+    /// Run from the start like `execute`, but lazily: the program gets its input from inputs rather
+    /// than the input function, and its outputs are yielded instead of going to the output
+    /// function. See `Machine::outputs` for when it ends.
+    ///
+    /// ```
+    /// use std::iter;
+    /// use nine1::Program;
+    ///
+    /// // Outputs its input, then counts up from it forever
+    /// let mut program = Program::new("3,11,4,11,1001,11,1,11,1105,1,2", || None, |_| {});
+    /// let outputs = program.outputs(iter::once(2)).take(3).collect::<Result<Vec<_>, _>>();
+    /// assert_eq!(outputs, Ok(vec![2, 3, 4]));
+    /// ```
+    pub fn outputs<In: IntoIterator<Item = Integer>>(&mut self, inputs: In) -> Outputs<'_, In::IntoIter> {
+        self.machine.restart(0, 0);
+        self.machine.outputs(inputs)
+    }
+
+    /// Run starting at pos with the given relative base, keeping the current memory
+    pub(crate) fn run_from(&mut self, pos: Position, relative_base: Integer) -> Result<Option<Integer>, IntcodeError> {
+        self.machine.restart(pos, relative_base);
+        self.resume().result()
+    }
+
+    /// Resume the machine until it halts, doing its IO through the program's functions
+    fn run(&mut self) -> Result<(), IntcodeError> {
         loop {
-            let opcode = self.get(pos);
-            let (instruction, modes) = read_opcode(opcode);
-            println!("\tpos: {}, opcode: {}, instruction: {:?}, modes: {:?}", pos, opcode, instruction, modes);
-            use Instruction::*;
-            match instruction {
-                Add => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.read_output_param(pos + 3, &modes[2]);
-                    self.set(c, a + b);
-                    pos += 4;
-                }
-                Multiply => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.read_output_param(pos + 3, &modes[2]);
-                    self.set(c, a * b);
-                    pos += 4;
-                }
-                Input => {
-                    let dest = self.read_output_param(pos + 1, &modes[0]);
-                    let input = self.read_input();
-                    self.set(dest, input);
-                    pos += 2;
-                }
-                Output => {
-                    let val = self.read_param(pos + 1, &modes[0]);
-                    self.write_output(val);
-                    prev_output = Some(val);
-                    pos += 2;
-                }
-                JumpIfTrue => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    if a != 0 {
-                        pos = b as Position;
-                    } else {
-                        pos += 3;
-                    }
-                }
-                JumpIfFalse => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    if a == 0 {
-                        pos = b as Position;
-                    } else {
-                        pos += 3;
-                    }
-                }
-                LessThan => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.read_output_param(pos + 3, &modes[2]);
-                    self.set(c, (a < b).try_into().unwrap());
-                    pos += 4;
-                }
-                Equals => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.read_output_param(pos + 3, &modes[2]);
-                    self.set(c, (a == b).try_into().unwrap());
-                    pos += 4;
-                }
-                RelativeBaseOffset => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    self.relative_base += a;
-                    println!("Relative base is now {}", self.relative_base);
-                    pos += 2;
-                }
-                Halt => {
-                    break;
+            match self.machine.resume()? {
+                State::Output(val) => (self.output_fn)(val),
+                State::NeedsInput => {
+                    let input = (self.input_fn)();
+                    log::debug!("Read input {:?}", input);
+                    let input = input.ok_or(IntcodeError::InputExhausted { pos: self.machine.pc() })?;
+                    self.machine.push_input(input);
                 }
+                State::Halted => return Ok(()),
             }
         }
-        prev_output
     }
 }
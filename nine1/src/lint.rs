@@ -0,0 +1,140 @@
+//! Checks a program for problems before running it, by following the code that can be reached
+//! from address 0. Jumps are only followed when their target is an immediate, and a jump with an
+//! immediate condition only goes the way it always goes. Code which modifies itself can reach
+//! more than this finds, and can fix up what it flags before it's run.
+//!
+//! ```
+//! use nine1::lint::{lint, Problem};
+//! use nine1::Extensions;
+//!
+//! let diagnostics = lint(&[1101, 1, 2, 9, 1105, 1, 100, 99], &Extensions::new());
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].problem, Problem::JumpOutOfRange { target: 100 });
+//! assert_eq!(diagnostics[0].to_string(), "4: Jump to 100 is outside of the program");
+//! ```
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{read_opcode, Extensions, Instruction, Integer, IntcodeError, Opcode, ParamMode, ParamModes, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// Last 2 digits of the opcode are neither a built-in instruction nor a registered extension
+    UnknownOpcode { opcode: Integer },
+    /// One of the mode digits of the opcode is not a known parameter mode
+    InvalidParamMode { opcode: Integer, mode: Integer },
+    /// The param with the index, counting from 1, is written to but is in IMMEDIATE mode
+    ImmediateWrite { param: usize },
+    /// Jump to an immediate address which is negative or past the end of the program
+    JumpOutOfRange { target: Integer },
+    /// The program ends before all of the instruction's params
+    Truncated { size: usize, available: usize },
+}
+
+/// Problem with the instruction at pos
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub pos: Position,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.pos)?;
+        match &self.problem {
+            Problem::UnknownOpcode { opcode } =>
+                write!(f, "Unknown opcode {}", opcode),
+            Problem::InvalidParamMode { opcode, mode } =>
+                write!(f, "Invalid param mode {} in opcode {}", mode, opcode),
+            Problem::ImmediateWrite { param } =>
+                write!(f, "Output param {} uses IMMEDIATE param mode", param),
+            Problem::JumpOutOfRange { target } =>
+                write!(f, "Jump to {} is outside of the program", target),
+            Problem::Truncated { size, available } =>
+                write!(f, "Instruction takes {} values but the program ends after {}", size, available),
+        }
+    }
+}
+
+/// What the linter knows about an instruction with its params
+struct Decoded {
+    /// None for extensions
+    instruction: Option<Instruction>,
+    modes: ParamModes,
+    params: Vec<Integer>,
+    /// Whether each param is written to
+    outputs: Vec<bool>,
+}
+
+/// Decode the instruction at pos, or give the problem which stops it from running
+fn decode(memory: &[Integer], pos: usize, extensions: &Extensions) -> Result<Decoded, Problem> {
+    let opcode = memory[pos];
+    let (instruction, modes, outputs): (_, _, Vec<bool>) = match read_opcode(pos as Position, opcode, extensions) {
+        Ok((Opcode::Builtin(instruction), modes)) => {
+            let outputs = (0..instruction.num_params()).map(|i| instruction.is_output_param(i)).collect();
+            (Some(instruction), modes, outputs)
+        }
+        Ok((Opcode::Extension(code), modes)) => {
+            let extension = extensions.get(code).expect("Decoded extension should be registered");
+            let num_params = extension.num_params();
+            let outputs = (0..num_params).map(|i| extension.has_output() && i == num_params - 1).collect();
+            (None, modes, outputs)
+        }
+        Err(IntcodeError::InvalidParamMode { mode, .. }) => return Err(Problem::InvalidParamMode { opcode, mode }),
+        Err(_) => return Err(Problem::UnknownOpcode { opcode }),
+    };
+    let size = 1 + outputs.len();
+    if pos + size > memory.len() {
+        return Err(Problem::Truncated { size, available: memory.len() - pos });
+    }
+    let params = memory[pos + 1..pos + size].to_vec();
+    Ok(Decoded { instruction, modes, params, outputs })
+}
+
+/// Problems found in the code reachable from address 0, in order of address
+pub fn lint(memory: &[Integer], extensions: &Extensions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![0];
+    while let Some(pos) = pending.pop() {
+        if pos >= memory.len() || !visited.insert(pos) {
+            continue;
+        }
+        let mut report = |problem| diagnostics.push(Diagnostic { pos: pos as Position, problem });
+        let decoded = match decode(memory, pos, extensions) {
+            Ok(decoded) => decoded,
+            Err(problem) => {
+                report(problem);
+                continue;
+            }
+        };
+        for (i, &output) in decoded.outputs.iter().enumerate() {
+            if output && decoded.modes[i] == ParamMode::IMMEDIATE {
+                report(Problem::ImmediateWrite { param: i + 1 });
+            }
+        }
+        let next = pos + 1 + decoded.params.len();
+        match decoded.instruction {
+            Some(Instruction::Halt) => {}
+            Some(instruction @ Instruction::JumpIfTrue) | Some(instruction @ Instruction::JumpIfFalse) => {
+                let taken = match (decoded.modes[0], decoded.params[0]) {
+                    (ParamMode::IMMEDIATE, cond) => Some((cond != 0) == (instruction == Instruction::JumpIfTrue)),
+                    _ => None,
+                };
+                if taken != Some(true) {
+                    pending.push(next);
+                }
+                match (decoded.modes[1], decoded.params[1]) {
+                    _ if taken == Some(false) => {}
+                    (ParamMode::IMMEDIATE, target) if target < 0 || target as usize >= memory.len() =>
+                        report(Problem::JumpOutOfRange { target }),
+                    (ParamMode::IMMEDIATE, target) => pending.push(target as usize),
+                    _ => {}
+                }
+            }
+            _ => pending.push(next),
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.pos);
+    diagnostics
+}
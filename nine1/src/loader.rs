@@ -0,0 +1,152 @@
+//! Loading programs from text which came from a file rather than a string literal.
+//!
+//! Values are separated by commas. Whitespace and line breaks around them are ignored, as is a
+//! comma after the last value, and `#` starts a comment which runs to the end of the line:
+//!
+//! ```text
+//! # Outputs its input
+//! 3,0,
+//! 4,0,
+//! 99
+//! ```
+//!
+//! `read_from` and `load` also accept programs in the binary format from the `image` module.
+//!
+//! ```
+//! use nine1::loader::{parse, LoadError};
+//!
+//! assert_eq!(parse("3,0,4,0,99\n").unwrap(), vec![3, 0, 4, 0, 99]);
+//! match parse("1,2,x,4") {
+//!     Err(LoadError::InvalidValue { index, text, .. }) => assert_eq!((index, text.as_str()), (2, "x")),
+//!     _ => unreachable!(),
+//! }
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use crate::image::{is_image, Image};
+use crate::{Integer, MEMORY_SIZE};
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// Field which isn't an integer, where index is the position it would have been loaded at
+    InvalidValue { index: usize, line: usize, text: String },
+    /// There were no values at all, only whitespace and comments
+    Empty,
+    /// Binary image which is truncated, malformed or in a version that isn't supported
+    InvalidImage(String),
+    /// Binary image whose contents don't match its checksum
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Program with more values than fit in a machine's memory
+    TooLarge { len: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::InvalidValue { index, line, text } if text.is_empty() =>
+                write!(f, "Missing value at index {} on line {}", index, line),
+            LoadError::InvalidValue { index, line, text } =>
+                write!(f, "Invalid value {:?} at index {} on line {}", text, index, line),
+            LoadError::Empty => write!(f, "Program is empty"),
+            LoadError::InvalidImage(reason) => write!(f, "{}", reason),
+            LoadError::ChecksumMismatch { expected, actual } =>
+                write!(f, "Image checksum is {:#010x} but its contents give {:#010x}", expected, actual),
+            LoadError::TooLarge { len } =>
+                write!(f, "Program has {} values but memory only holds {}", len, MEMORY_SIZE),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+fn parse_field(field: &str, index: usize, line: usize) -> Result<Integer, LoadError> {
+    let text = field.trim();
+    text.parse().map_err(|_| LoadError::InvalidValue { index, line, text: text.to_owned() })
+}
+
+/// Parse intcode text into the initial memory of a program
+pub fn parse(text: &str) -> Result<Vec<Integer>, LoadError> {
+    let mut values = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+//    Line the current field starts on, which is where its first non-whitespace character is
+    let mut field_line = None;
+    let mut in_comment = false;
+    for c in text.chars() {
+        match c {
+            '\n' => {
+                in_comment = false;
+                line += 1;
+                field.push(c);
+            }
+            _ if in_comment => {}
+            '#' => in_comment = true,
+            ',' => {
+                values.push(parse_field(&field, values.len(), field_line.unwrap_or(line))?);
+                field.clear();
+                field_line = None;
+            }
+            _ => {
+                if !c.is_whitespace() && field_line.is_none() {
+                    field_line = Some(line);
+                }
+                field.push(c);
+            }
+        }
+    }
+    match field_line {
+        Some(field_line) => values.push(parse_field(&field, values.len(), field_line)?),
+        None if values.is_empty() => return Err(LoadError::Empty),
+//        Nothing after the last comma
+        None => {}
+    }
+    check_size(values)
+}
+
+fn check_size(values: Vec<Integer>) -> Result<Vec<Integer>, LoadError> {
+    if values.len() > MEMORY_SIZE {
+        return Err(LoadError::TooLarge { len: values.len() });
+    }
+    Ok(values)
+}
+
+/// Read a program from anything which gives intcode text or a binary image
+pub fn read_from<R: Read>(mut reader: R) -> Result<Vec<Integer>, LoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if is_image(&bytes) {
+        return check_size(Image::from_bytes(&bytes)?.memory);
+    }
+    let text = String::from_utf8(bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    parse(&text)
+}
+
+/// Read a program from the intcode text or image file at path
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Integer>, LoadError> {
+    read_from(BufReader::new(File::open(path)?))
+}
+
+/// Intcode text for the memory, which `parse` turns back into the same values
+pub fn to_text(memory: &[Integer]) -> String {
+    memory.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(",")
+}
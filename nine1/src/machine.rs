@@ -0,0 +1,498 @@
+//! Resumable machine which holds all of a program's state but none of its IO, so it can be
+//! paused whenever it outputs or wants input and cloned to try several futures from one point.
+//! Clones share memory pages until they write to them, see `Memory`.
+//!
+//! ```
+//! use nine1::{Machine, State};
+//!
+//! // Outputs double its input until it reads 0
+//! let mut machine = Machine::new(vec![3, 20, 1006, 20, 14, 1002, 20, 2, 21, 4, 21, 1105, 1, 0, 99]);
+//! assert_eq!(machine.resume(), Ok(State::NeedsInput));
+//! let mut fork = machine.clone();
+//!
+//! machine.push_input(21);
+//! assert_eq!(machine.resume(), Ok(State::Output(42)));
+//! assert_eq!(machine.resume(), Ok(State::NeedsInput));
+//!
+//! fork.push_input(0);
+//! assert_eq!(fork.resume(), Ok(State::Halted));
+//! ```
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::convert::{TryFrom, TryInto};
+use std::iter::FusedIterator;
+
+use crate::{code_addresses, diff_memory, read_opcode, Coverage, Devices, Event, ExtensionAction, Extensions,
+            Instruction, Integer, IntcodeError, Memory, MemoryDiff, Opcode, ParamMode, ParamModes, Position,
+            Session, Stats, MEMORY_SIZE};
+
+/// Why a machine stopped running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    /// Output the value, and carries on after it when resumed
+    Output(Integer),
+    /// Reached an input instruction with no input queued, which it finishes once given some
+    NeedsInput,
+    /// Reached a halt, or an extension halted it
+    Halted,
+}
+
+#[derive(Clone)]
+pub struct Machine {
+    data: Memory,
+    pc: Position,
+    relative_base: Integer,
+    /// Queued input values, read first to last
+    input: VecDeque<Integer>,
+    /// Destination of an input instruction which is waiting for a value
+    waiting: Option<Position>,
+    halted: bool,
+    extensions: Extensions,
+    halt_code: Option<Integer>,
+    stats: Stats,
+    /// Times each opcode (without its modes) was executed, kept apart from the stats as an
+    /// array since it's updated every cycle
+    opcode_counts: [u64; 100],
+    highest_address: Cell<Option<Position>>,
+    devices: Devices,
+    coverage: Option<Coverage>,
+    session: Option<Session>,
+    cycle_limit: Option<u64>,
+}
+
+impl Machine {
+    /// Machine about to run the given memory from the start
+    pub fn new(memory: Vec<Integer>) -> Self {
+        Self {
+            data: Memory::new(memory),
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            waiting: None,
+            halted: false,
+            extensions: Extensions::new(),
+            halt_code: None,
+            stats: Stats::default(),
+            opcode_counts: [0; 100],
+            highest_address: Cell::new(None),
+            devices: Devices::new(),
+            coverage: None,
+            session: None,
+            cycle_limit: None,
+        }
+    }
+
+    /// Use the given registry to decode opcodes which aren't built in
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Map devices onto memory, see the `device` module for details. Clones share the devices.
+    pub fn with_devices(mut self, devices: Devices) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    /// Record which instructions and branches get executed
+    pub fn with_coverage(mut self) -> Self {
+        self.coverage = Some(Coverage::new());
+        self
+    }
+
+    /// Record inputs, outputs and halting so the run can be replayed
+    pub fn with_recording(mut self) -> Self {
+        self.session = Some(Session::new());
+        self
+    }
+
+    /// Stop with an error instead of running more than the given number of cycles
+    pub fn with_cycle_limit(mut self, cycles: u64) -> Self {
+        self.cycle_limit = Some(cycles);
+        self
+    }
+
+    /// Go back to pos with the given relative base, keeping the current memory but forgetting
+    /// any queued input and starting the cycle count and other stats again
+    pub fn restart(&mut self, pos: Position, relative_base: Integer) {
+        self.pc = pos;
+        self.relative_base = relative_base;
+        self.input.clear();
+        self.waiting = None;
+        self.halted = false;
+        self.halt_code = None;
+        self.stats = Stats::starting_at(relative_base);
+        self.opcode_counts = [0; 100];
+        self.highest_address.set(None);
+    }
+
+    /// Queue a value for the program's next input instruction
+    pub fn push_input(&mut self, val: Integer) {
+        self.input.push_back(val);
+    }
+
+    /// Position of the next instruction to run
+    pub fn pc(&self) -> Position {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> Integer {
+        self.relative_base
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.data
+    }
+
+    /// Exit code given by an extension which halted the program
+    pub fn halt_code(&self) -> Option<Integer> {
+        self.halt_code
+    }
+
+    /// Number of instructions executed since the machine was created or restarted
+    pub fn cycles(&self) -> u64 {
+        self.stats.cycles
+    }
+
+    /// Counts of what happened since the machine was created or restarted
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats.clone();
+        for (opcode, &count) in self.opcode_counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            match Instruction::try_from(opcode as u8) {
+                Ok(instruction) => stats.instructions.insert(instruction, count),
+                Err(_) => stats.extensions.insert(opcode as u8, count),
+            };
+        }
+        stats.highest_address = self.highest_address.get();
+        stats
+    }
+
+    /// Coverage collected so far, if enabled with `with_coverage`
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Session recorded so far, if enabled with `with_recording`
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Iterator which runs the machine from where it is, feeding it the inputs whenever it reads
+    /// one and yielding each output in turn. It ends when the program halts, or after yielding an
+    /// error, which is `InputExhausted` if the program wants more input than was given.
+    pub fn outputs<In: IntoIterator<Item = Integer>>(&mut self, inputs: In) -> Outputs<'_, In::IntoIter> {
+        Outputs { machine: self, inputs: inputs.into_iter(), done: false }
+    }
+
+    /// Changes from this machine's memory to the other's. Code is told apart from data by the
+    /// coverage of both machines if either recorded any, or else by disassembling this one.
+    pub fn memory_diff(&self, other: &Machine) -> MemoryDiff {
+        let coverage = match (&self.coverage, &other.coverage) {
+            (None, None) => None,
+            (ours, theirs) => {
+                let mut merged = Coverage::new();
+                for coverage in ours.iter().chain(theirs.iter()) {
+                    merged.merge(coverage);
+                }
+                Some(merged)
+            }
+        };
+        let (ours, theirs) = (self.data.to_vec(), other.data.to_vec());
+        let code = code_addresses(&ours, coverage.as_ref(), &self.extensions);
+        diff_memory(&ours, &theirs, &code)
+    }
+
+    fn touch(&self, pos: Position) {
+        if self.highest_address.get().is_none_or(|highest| pos > highest) {
+            self.highest_address.set(Some(pos));
+        }
+    }
+
+    /// Error unless pos is in memory. Addresses from params are checked by `to_address` first, so
+    /// this only fails when the instruction pointer runs off the end.
+    fn check_in_memory(pos: Position) -> Result<(), IntcodeError> {
+        if pos >= MEMORY_SIZE as Position {
+            return Err(IntcodeError::AddressOutOfRange { pos, address: pos as Integer });
+        }
+        Ok(())
+    }
+
+    /// Get value stored at position
+    fn get(&self, pos: Position) -> Result<Integer, IntcodeError> {
+        Self::check_in_memory(pos)?;
+        self.touch(pos);
+        if !self.devices.is_empty() {
+            if let Some(val) = self.devices.read(pos) {
+                return Ok(val);
+            }
+        }
+        Ok(self.data[pos as usize])
+    }
+
+    fn to_address(pos: Position, address: Integer) -> Result<Position, IntcodeError> {
+        let checked: Position = address.try_into().map_err(|_| IntcodeError::NegativeAddress { pos, address })?;
+        if checked >= MEMORY_SIZE as Position {
+            return Err(IntcodeError::AddressOutOfRange { pos, address });
+        }
+        Ok(checked)
+    }
+
+    /// Address a relative mode param at pos refers to
+    fn relative_address(&self, pos: Position, offset: Integer) -> Result<Position, IntcodeError> {
+        let address = offset.checked_add(self.relative_base).ok_or(IntcodeError::Overflow { pos })?;
+        Self::to_address(pos, address)
+    }
+
+    fn read_param(&self, pos: Position, mode: &ParamMode) -> Result<Integer, IntcodeError> {
+        let param_val = self.get(pos)?;
+        match mode {
+            ParamMode::POSITION => self.get(Self::to_address(pos, param_val)?),
+            ParamMode::IMMEDIATE => Ok(param_val),
+            ParamMode::RELATIVE => self.get(self.relative_address(pos, param_val)?),
+        }
+    }
+
+    fn read_output_param(&self, pos: Position, mode: &ParamMode) -> Result<Position, IntcodeError> {
+        let param_val = self.get(pos)?;
+        match mode {
+            ParamMode::IMMEDIATE => Err(IntcodeError::ImmediateWrite { pos }),
+            ParamMode::POSITION => Self::to_address(pos, param_val),
+            ParamMode::RELATIVE => self.relative_address(pos, param_val),
+        }
+    }
+
+    fn set(&mut self, pos: Position, val: Integer) -> Result<(), IntcodeError> {
+        Self::check_in_memory(pos)?;
+        self.touch(pos);
+        if !self.devices.is_empty() && self.devices.write(pos, val) {
+            log::trace!("\t\tWrote {} to device at {}", val, pos);
+            return Ok(());
+        }
+        self.data.set(pos as usize, val);
+        log::trace!("\t\tSet {} to {}", pos, val);
+        Ok(())
+    }
+
+    fn record_event(&mut self, event: Event) {
+        if let Some(session) = &mut self.session {
+            session.record(event);
+        }
+    }
+
+    fn record_branch(&mut self, pos: Position, taken: bool) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_branch(pos, taken);
+        }
+    }
+
+    /// Finish the waiting input instruction if there's a value queued for it
+    fn finish_input(&mut self, dest: Position) -> Result<bool, IntcodeError> {
+        match self.input.pop_front() {
+            Some(input) => {
+                log::debug!("Read input {}", input);
+                self.stats.inputs += 1;
+                self.record_event(Event::Input { cycle: self.stats.cycles, pos: self.pc, value: input });
+                self.set(dest, input)?;
+                self.waiting = None;
+                self.pc += 2;
+                Ok(true)
+            }
+            None => {
+                self.waiting = Some(dest);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Run the extension registered for the opcode at pos, returning the position to continue at
+    /// or None if it halted the program
+    fn execute_extension(&mut self, pos: Position, opcode: u8, modes: &ParamModes) -> Result<Option<Position>, IntcodeError> {
+        let extension = self.extensions.get(opcode).expect("Decoded extension should be registered");
+        let num_params = extension.num_params();
+        let num_inputs = if extension.has_output() { num_params - 1 } else { num_params };
+        let params = (0..num_inputs)
+            .map(|i| self.read_param(pos + 1 + i as Position, &modes[i]))
+            .collect::<Result<Vec<_>, _>>()?;
+        let next = pos + 1 + num_params as Position;
+        let dest = if extension.has_output() {
+            Some(self.read_output_param(next - 1, &modes[num_params - 1])?)
+        } else {
+            None
+        };
+        match extension.call(&params) {
+            ExtensionAction::Continue => Ok(Some(next)),
+            ExtensionAction::Store(val) => {
+                let dest = dest.ok_or(IntcodeError::MissingOutputParam { pos, opcode })?;
+                self.set(dest, val)?;
+                Ok(Some(next))
+            }
+            ExtensionAction::Jump(target) => Ok(Some(Self::to_address(pos, target)?)),
+            ExtensionAction::Halt(code) => {
+                self.halt_code = Some(code);
+                self.record_event(Event::Halt { cycle: self.stats.cycles, pos });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Run until the program outputs a value, wants input which hasn't been queued, or halts.
+    /// On an error the machine stays at the instruction which caused it.
+    pub fn resume(&mut self) -> Result<State, IntcodeError> {
+        if self.halted {
+            return Ok(State::Halted);
+        }
+        if let Some(dest) = self.waiting {
+            if !self.finish_input(dest)? {
+                return Ok(State::NeedsInput);
+            }
+        }
+        loop {
+            let pos = self.pc;
+            if self.cycle_limit.is_some_and(|limit| self.stats.cycles >= limit) {
+                return Err(IntcodeError::CycleLimit { pos, cycles: self.stats.cycles });
+            }
+            self.devices.tick();
+            let opcode = self.get(pos)?;
+            self.stats.cycles += 1;
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record_instruction(pos);
+            }
+            let decoded = read_opcode(pos, opcode, &self.extensions)?;
+            self.opcode_counts[(opcode % 100) as usize] += 1;
+            let (instruction, modes) = match decoded {
+                (Opcode::Builtin(instruction), modes) => (instruction, modes),
+                (Opcode::Extension(code), modes) => {
+                    log::trace!("\tpos: {}, opcode: {}, extension: {}, modes: {:?}", pos, opcode, code, modes);
+                    match self.execute_extension(pos, code, &modes)? {
+                        Some(next) => {
+                            self.pc = next;
+                            continue;
+                        }
+                        None => {
+                            self.halted = true;
+                            return Ok(State::Halted);
+                        }
+                    }
+                }
+            };
+            log::trace!("\tpos: {}, opcode: {}, instruction: {:?}, modes: {:?}", pos, opcode, instruction, modes);
+            use Instruction::*;
+            match instruction {
+                Add => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    let c = self.read_output_param(pos + 3, &modes[2])?;
+                    self.set(c, a.checked_add(b).ok_or(IntcodeError::Overflow { pos })?)?;
+                    self.pc += 4;
+                }
+                Multiply => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    let c = self.read_output_param(pos + 3, &modes[2])?;
+                    self.set(c, a.checked_mul(b).ok_or(IntcodeError::Overflow { pos })?)?;
+                    self.pc += 4;
+                }
+                Input => {
+                    let dest = self.read_output_param(pos + 1, &modes[0])?;
+                    if !self.finish_input(dest)? {
+                        return Ok(State::NeedsInput);
+                    }
+                }
+                Output => {
+                    let val = self.read_param(pos + 1, &modes[0])?;
+                    log::debug!("Outputting {}", val);
+                    self.stats.outputs += 1;
+                    self.stats.last_output = Some(val);
+                    self.record_event(Event::Output { cycle: self.stats.cycles, pos, value: val });
+                    self.pc += 2;
+                    return Ok(State::Output(val));
+                }
+                JumpIfTrue => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    self.record_branch(pos, a != 0);
+                    if a != 0 {
+                        self.pc = Self::to_address(pos, b)?;
+                    } else {
+                        self.pc += 3;
+                    }
+                }
+                JumpIfFalse => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    self.record_branch(pos, a == 0);
+                    if a == 0 {
+                        self.pc = Self::to_address(pos, b)?;
+                    } else {
+                        self.pc += 3;
+                    }
+                }
+                LessThan => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    let c = self.read_output_param(pos + 3, &modes[2])?;
+                    self.set(c, (a < b).into())?;
+                    self.pc += 4;
+                }
+                Equals => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    let b = self.read_param(pos + 2, &modes[1])?;
+                    let c = self.read_output_param(pos + 3, &modes[2])?;
+                    self.set(c, (a == b).into())?;
+                    self.pc += 4;
+                }
+                RelativeBaseOffset => {
+                    let a = self.read_param(pos + 1, &modes[0])?;
+                    self.relative_base = self.relative_base.checked_add(a).ok_or(IntcodeError::Overflow { pos })?;
+                    self.stats.record_relative_base(self.relative_base);
+                    log::trace!("Relative base is now {}", self.relative_base);
+                    self.pc += 2;
+                }
+                Halt => {
+                    self.record_event(Event::Halt { cycle: self.stats.cycles, pos });
+                    self.halted = true;
+                    return Ok(State::Halted);
+                }
+            }
+        }
+    }
+}
+
+/// Outputs of a machine, see `Machine::outputs`. The machine only runs while the next output is
+/// asked for, so dropping the iterator leaves it paused after the last one.
+pub struct Outputs<'a, In> {
+    machine: &'a mut Machine,
+    inputs: In,
+    done: bool,
+}
+
+impl<'a, In: Iterator<Item = Integer>> Iterator for Outputs<'a, In> {
+    type Item = Result<Integer, IntcodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.machine.resume() {
+                Ok(State::Output(val)) => return Some(Ok(val)),
+                Ok(State::NeedsInput) => match self.inputs.next() {
+                    Some(input) => self.machine.push_input(input),
+                    None => {
+                        self.done = true;
+                        return Some(Err(IntcodeError::InputExhausted { pos: self.machine.pc() }));
+                    }
+                },
+                Ok(State::Halted) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a, In: Iterator<Item = Integer>> FusedIterator for Outputs<'a, In> {}
@@ -7,9 +7,11 @@ fn main() {
 //    let intcode_program = "109,1,203,2,204,2,99";
 
     let mut program = Program::new(intcode_program,
-                               || 2,
+                               || Some(2),
                                |val| println!("{}", val));
 
-    let output = program.execute().expect("Program didn't output anything");
+    let output = program.execute().result()
+        .expect("Program failed")
+        .expect("Program didn't output anything");
     println!("Output: {:?}.", output)
 }
@@ -0,0 +1,135 @@
+//! Comparing the memory of two runs, or of one run at two points, e.g. to see where amplifiers
+//! with different phase settings go their separate ways:
+//!
+//! ```
+//! use nine1::{Program, Region};
+//!
+//! let intcode = "3,11,1002,11,2,12,4,12,99,0,0,0,0";
+//! let mut first = Program::new(intcode, || Some(1), |_| {});
+//! let mut second = Program::new(intcode, || Some(5), |_| {});
+//! first.execute().result().unwrap();
+//! second.execute().result().unwrap();
+//!
+//! let diff = first.memory_diff(&second);
+//! assert_eq!(diff.ranges.len(), 1);
+//! assert_eq!((diff.ranges[0].start, diff.ranges[0].region), (11, Region::Data));
+//! assert_eq!((&diff.ranges[0].old[..], &diff.ranges[0].new[..]), (&[1, 2][..], &[5, 10][..]));
+//! ```
+//!
+//! Changed cells are grouped into ranges of neighbouring addresses, which are split where they go
+//! from code to data. Code is where executed instructions are, if coverage was recorded, and
+//! otherwise whatever a linear sweep of the memory decodes as instructions.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::disasm::decode_line;
+use crate::{disassemble, Coverage, Extensions, Integer, Line, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Code,
+    Data,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Region::Code => write!(f, "code"),
+            Region::Data => write!(f, "data"),
+        }
+    }
+}
+
+/// Neighbouring addresses which changed, all in code or all in data
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedRange {
+    pub start: Position,
+    pub old: Vec<Integer>,
+    pub new: Vec<Integer>,
+    pub region: Region,
+}
+
+impl ChangedRange {
+    /// Address after the last one in the range
+    pub fn end(&self) -> Position {
+        self.start + self.old.len() as Position
+    }
+}
+
+fn join(values: &[Integer]) -> String {
+    values.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(",")
+}
+
+impl fmt::Display for ChangedRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let range = format!("{}..{}", self.start, self.end());
+        write!(f, "{:>15} {}: {} -> {}", range, self.region, join(&self.old), join(&self.new))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryDiff {
+    pub ranges: Vec<ChangedRange>,
+}
+
+impl MemoryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Number of addresses which changed
+    pub fn changed(&self) -> usize {
+        self.ranges.iter().map(|range| range.old.len()).sum()
+    }
+}
+
+impl fmt::Display for MemoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for range in self.ranges.iter() {
+            writeln!(f, "{}", range)?;
+        }
+        Ok(())
+    }
+}
+
+/// Addresses holding instructions: those of the executed instructions if there's coverage, or
+/// else the instructions found by disassembling memory up to the last non-zero value
+pub fn code_addresses(memory: &[Integer], coverage: Option<&Coverage>, extensions: &Extensions) -> BTreeSet<Position> {
+    let lines = match coverage {
+        Some(coverage) => coverage.executed_positions()
+            .filter(|&pos| (pos as usize) < memory.len())
+            .map(|pos| decode_line(memory, pos as usize, extensions))
+            .collect(),
+        None => {
+            let used = memory.iter().rposition(|&val| val != 0).map_or(0, |last| last + 1);
+            disassemble(&memory[..used], extensions)
+        }
+    };
+    lines.iter()
+        .filter(|line| matches!(line, Line::Instruction { .. }))
+        .flat_map(|line| line.pos()..line.pos() + line.size() as Position)
+        .collect()
+}
+
+/// Changes from the old memory to the new one, where code holds the addresses of instructions.
+/// Memories of different lengths are compared as if the shorter one was padded with zeros.
+pub fn diff_memory(old: &[Integer], new: &[Integer], code: &BTreeSet<Position>) -> MemoryDiff {
+    let mut diff = MemoryDiff::default();
+    for pos in 0..old.len().max(new.len()) {
+        let old_val = old.get(pos).cloned().unwrap_or(0);
+        let new_val = new.get(pos).cloned().unwrap_or(0);
+        if old_val == new_val {
+            continue;
+        }
+        let pos = pos as Position;
+        let region = if code.contains(&pos) { Region::Code } else { Region::Data };
+        match diff.ranges.last_mut() {
+            Some(range) if range.end() == pos && range.region == region => {
+                range.old.push(old_val);
+                range.new.push(new_val);
+            }
+            _ => diff.ranges.push(ChangedRange { start: pos, old: vec![old_val], new: vec![new_val], region }),
+        }
+    }
+    diff
+}
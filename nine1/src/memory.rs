@@ -0,0 +1,116 @@
+//! Copy-on-write memory made of fixed-size pages, so cloning a machine only copies the page
+//! table and clones share every page until one of them writes to it. Pages which have only
+//! ever held zeros aren't allocated at all.
+//!
+//! ```
+//! use nine1::Memory;
+//!
+//! let mut memory = Memory::new(vec![1, 2, 3]);
+//! let mut fork = memory.clone();
+//! assert_eq!(memory.shared_pages(&fork), 1);
+//!
+//! fork.set(1, 20);
+//! memory.set(5000, 7);
+//! assert_eq!((memory[1], fork[1]), (2, 20));
+//! assert_eq!((memory[5000], fork[5000]), (7, 0));
+//! assert_eq!(memory.shared_pages(&fork), 0);
+//! ```
+use std::ops::Index;
+use std::rc::Rc;
+
+use crate::{Integer, MEMORY_SIZE};
+
+/// Number of cells in a page
+pub const PAGE_SIZE: usize = 4096;
+
+type Page = Rc<Vec<Integer>>;
+
+static ZERO: Integer = 0;
+
+#[derive(Clone, Debug)]
+pub struct Memory {
+    /// None for pages which are all zeros
+    pages: Vec<Option<Page>>,
+}
+
+impl Memory {
+    /// Memory of `MEMORY_SIZE` cells starting with the given values, followed by zeros
+    pub fn new(values: Vec<Integer>) -> Self {
+        assert!(values.len() <= MEMORY_SIZE, "Program of {} values doesn't fit in memory", values.len());
+        let mut pages = vec![None; MEMORY_SIZE.div_ceil(PAGE_SIZE)];
+        for (page, chunk) in pages.iter_mut().zip(values.chunks(PAGE_SIZE)) {
+            if chunk.iter().any(|&val| val != 0) {
+                let mut values = chunk.to_vec();
+                values.resize(PAGE_SIZE, 0);
+                *page = Some(Rc::new(values));
+            }
+        }
+        Self { pages }
+    }
+
+    pub fn len(&self) -> usize {
+        MEMORY_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Value at pos. Panics if pos is past the end of memory, like indexing a slice.
+    pub fn get(&self, pos: usize) -> Integer {
+        self[pos]
+    }
+
+    /// Write the value to pos, copying its page first if another clone shares it. Panics if pos is
+    /// past the end of memory.
+    pub fn set(&mut self, pos: usize, val: Integer) {
+        assert!(pos < MEMORY_SIZE, "Address {} is past the end of memory", pos);
+        let page = &mut self.pages[pos / PAGE_SIZE];
+        if page.is_none() && val == 0 {
+            return;
+        }
+        let page = page.get_or_insert_with(|| Rc::new(vec![0; PAGE_SIZE]));
+        Rc::make_mut(page)[pos % PAGE_SIZE] = val;
+    }
+
+    /// Number of cells up to and including the last non-zero one
+    pub fn used_len(&self) -> usize {
+        self.pages.iter().enumerate().rev()
+            .filter_map(|(index, page)| page.as_ref().map(|page| (index, page)))
+            .find_map(|(index, page)| page.iter().rposition(|&val| val != 0).map(|last| index * PAGE_SIZE + last + 1))
+            .unwrap_or(0)
+    }
+
+    /// Values up to the last non-zero one, leaving out the zeroed memory after it
+    pub fn to_vec(&self) -> Vec<Integer> {
+        let used = self.used_len();
+        (0..used).map(|pos| self[pos]).collect()
+    }
+
+    /// Number of pages which have been allocated, i.e. written with something other than zero
+    pub fn allocated_pages(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+
+    /// Number of allocated pages which this memory and the other still share
+    pub fn shared_pages(&self, other: &Memory) -> usize {
+        self.pages.iter().zip(other.pages.iter())
+            .filter(|(ours, theirs)| match (ours, theirs) {
+                (Some(ours), Some(theirs)) => Rc::ptr_eq(ours, theirs),
+                _ => false,
+            })
+            .count()
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Integer;
+
+    fn index(&self, pos: usize) -> &Integer {
+        assert!(pos < MEMORY_SIZE, "Address {} is past the end of memory", pos);
+        match &self.pages[pos / PAGE_SIZE] {
+            Some(page) => &page[pos % PAGE_SIZE],
+            None => &ZERO,
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Peephole optimizer which rewrites instructions in place. Intcode addresses are baked into the
+//! program, so nothing can move: every rewrite keeps the instruction the same size.
+//!
+//! - Arithmetic and comparisons on immediates are folded into storing the result
+//! - Multiplying by 1 or adding 0 becomes a plain copy, and multiplying by 0 stores 0
+//! - Jumps which land on a jump that's never taken, or on one that's always taken, go straight to
+//!   where that jump would end up
+//! - Dead jumps (never taken, or only going to the next instruction) which can no longer be
+//!   reached are removed
+//!
+//! Rewriting is only safe when the rewritten code is never read or written by the program, so the
+//! analysis is conservative. Programs which use relative mode could touch any address, and
+//! programs which write to their own instructions, might execute values they wrote, jump anywhere
+//! other than the start of an instruction or jump to addresses computed while running are left
+//! alone entirely. Other programs only have instructions rewritten which no position mode param
+//! refers to.
+//!
+//! ```
+//! use nine1::optimizer::{optimize, Change};
+//!
+//! let optimized = optimize(&[1102, 6, 7, 7, 4, 7, 99, 0]);
+//! assert_eq!(optimized.memory, vec![1101, 42, 0, 7, 4, 7, 99, 0]);
+//! assert_eq!(optimized.changes, vec![Change::Folded { pos: 0, value: 42 }]);
+//! ```
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::disasm::{decode_builtins, Decoded};
+use crate::{Instruction, Integer, ParamMode, Position, MEMORY_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Arithmetic or comparison on immediates replaced by storing its result
+    Folded { pos: Position, value: Integer },
+    /// Multiplication by 1 or addition of 0 replaced by a copy
+    Copy { pos: Position },
+    /// Jump retargeted past dead jumps or through an unconditional jump
+    Threaded { pos: Position, from: Position, to: Position },
+    /// Unreachable jump which would never have gone anywhere, replaced by zeros
+    RemovedDeadJump { pos: Position },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Folded { pos, value } => write!(f, "{}: folded to {}", pos, value),
+            Change::Copy { pos } => write!(f, "{}: simplified to a copy", pos),
+            Change::Threaded { pos, from, to } => write!(f, "{}: jump to {} now goes to {}", pos, from, to),
+            Change::RemovedDeadJump { pos } => write!(f, "{}: removed dead jump", pos),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimized {
+    pub memory: Vec<Integer>,
+    pub changes: Vec<Change>,
+    /// Why the program was left alone, if it couldn't be shown that rewriting it is safe
+    pub not_optimized: Option<String>,
+}
+
+impl Optimized {
+    /// Optimized program as intcode text
+    pub fn intcode(&self) -> String {
+        self.memory.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(",")
+    }
+}
+
+fn is_jump(instruction: Instruction) -> bool {
+    matches!(instruction, Instruction::JumpIfTrue | Instruction::JumpIfFalse)
+}
+
+/// Whether a jump with an immediate condition is taken
+fn is_taken(d: &Decoded) -> Option<bool> {
+    match d.params[0] {
+        (ParamMode::IMMEDIATE, cond) => Some((cond != 0) == (d.instruction == Instruction::JumpIfTrue)),
+        _ => None,
+    }
+}
+
+/// Immediate target of a jump
+fn target(d: &Decoded) -> Option<Position> {
+    match d.params[1] {
+        (ParamMode::IMMEDIATE, target) if target >= 0 => Some(target as Position),
+        _ => None,
+    }
+}
+
+/// Whether execution can continue with the next instruction
+fn falls_through(d: &Decoded) -> bool {
+    match d.instruction {
+        Instruction::Halt => false,
+        instruction if is_jump(instruction) => !(is_taken(d) == Some(true) && target(d).is_some()),
+        _ => true,
+    }
+}
+
+/// Jump which never goes anywhere except the next instruction
+fn is_dead_jump(d: &Decoded) -> bool {
+    is_jump(d.instruction) && match is_taken(d) {
+        Some(false) => true,
+        Some(true) => target(d) == Some(d.next()),
+        None => false,
+    }
+}
+
+/// Reading the param has no effect other than giving its value
+fn is_safe_read(param: (ParamMode, Integer)) -> bool {
+    match param {
+        (ParamMode::IMMEDIATE, _) => true,
+        (ParamMode::POSITION, address) => address >= 0 && (address as usize) < MEMORY_SIZE,
+        (ParamMode::RELATIVE, _) => false,
+    }
+}
+
+/// Addresses the program reads and writes through position mode params
+fn analyse(decoded: &[Decoded]) -> Result<BTreeSet<Position>, String> {
+    let mut touched = BTreeSet::new();
+    let mut written = BTreeSet::new();
+    for d in decoded {
+        for (i, &(mode, val)) in d.params.iter().enumerate() {
+            match mode {
+                ParamMode::RELATIVE => return Err(format!(
+                    "Instruction at {} uses relative mode, so any address could be read or written", d.pos())),
+                ParamMode::POSITION if val >= 0 => {
+                    touched.insert(val as Position);
+                    if d.instruction.is_output_param(i) {
+                        written.insert(val as Position);
+                    }
+                }
+                _ => {}
+            }
+            if is_jump(d.instruction) && i == 1 && mode != ParamMode::IMMEDIATE {
+                return Err(format!("Jump at {} has a target which isn't known until it runs", d.pos()));
+            }
+        }
+    }
+
+    let code: BTreeSet<Position> = decoded.iter().flat_map(|d| d.pos()..d.next()).collect();
+    if let Some(pos) = written.intersection(&code).next() {
+        return Err(format!("Program writes to its own code at {}", pos));
+    }
+//    Values written outside of the decoded code could be executed as instructions we know nothing
+//    about, if anything can get there
+    let targets: BTreeSet<Position> = decoded.iter()
+        .filter(|d| is_jump(d.instruction) && is_taken(d) != Some(false))
+        .filter_map(target)
+        .collect();
+    for &pos in written.iter() {
+        if targets.contains(&pos) || decoded.iter().any(|d| d.next() == pos && falls_through(d)) {
+            return Err(format!("Program might execute the value it writes to {}", pos));
+        }
+    }
+//    A jump anywhere else than the start of an instruction runs values as code which the rewrites
+//    don't know about, such as the params of an instruction that gets folded
+    let starts: BTreeSet<Position> = decoded.iter().map(Decoded::pos).collect();
+    if let Some(pos) = targets.difference(&starts).next() {
+        return Err(format!("Program jumps to {}, which isn't the start of an instruction", pos));
+    }
+    Ok(touched)
+}
+
+fn opcode(instruction: Instruction, modes: &[ParamMode]) -> Integer {
+    modes.iter().rev().fold(0, |acc, &mode| acc * 10 + mode as Integer) * 100 + instruction as Integer
+}
+
+/// Same-sized replacement for an arithmetic or comparison instruction
+fn simplify(d: &Decoded) -> Option<(Vec<Integer>, Change)> {
+    use Instruction::*;
+    let pos = d.pos();
+    let (a, b, (dest_mode, dest)) = (d.params[0], d.params[1], d.params[2]);
+    let constant = |value: Integer| {
+        let code = vec![opcode(Add, &[ParamMode::IMMEDIATE, ParamMode::IMMEDIATE, dest_mode]), value, 0, dest];
+        (code, Change::Folded { pos, value })
+    };
+    let copy = |(mode, val): (ParamMode, Integer)| {
+        let code = vec![opcode(Add, &[mode, ParamMode::IMMEDIATE, dest_mode]), val, 0, dest];
+        (code, Change::Copy { pos })
+    };
+    let imm = |param: (ParamMode, Integer)| match param {
+        (ParamMode::IMMEDIATE, val) => Some(val),
+        _ => None,
+    };
+
+    let replacement = match (d.instruction, imm(a), imm(b)) {
+        (Add, Some(x), Some(y)) => constant(x.checked_add(y)?),
+        (Multiply, Some(x), Some(y)) => constant(x.checked_mul(y)?),
+        (LessThan, Some(x), Some(y)) => constant((x < y) as Integer),
+        (Equals, Some(x), Some(y)) => constant((x == y) as Integer),
+        (Add, None, Some(0)) | (Multiply, None, Some(1)) => copy(a),
+        (Add, Some(0), None) | (Multiply, Some(1), None) => copy(b),
+        (Multiply, None, Some(0)) if is_safe_read(a) => constant(0),
+        (Multiply, Some(0), None) if is_safe_read(b) => constant(0),
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+/// Where a jump to target ends up after skipping dead jumps and following unconditional ones
+fn thread(target: Position, jumps: &BTreeMap<Position, &Decoded>) -> Position {
+    let mut visited = BTreeSet::new();
+    let mut pos = target;
+    while visited.insert(pos) {
+        let next = match jumps.get(&pos) {
+            Some(j) if is_taken(j) == Some(false) => j.next(),
+            Some(j) if is_taken(j) == Some(true) => match self::target(j) {
+                Some(next) => next,
+                None => break,
+            },
+            _ => break,
+        };
+//        Jumps which go around in circles are left as they are
+        if visited.contains(&next) {
+            return target;
+        }
+        pos = next;
+    }
+    pos
+}
+
+/// Optimize the program, which is left as it is if the rewrites can't be shown to be safe
+pub fn optimize(memory: &[Integer]) -> Optimized {
+    let decoded = decode_builtins(memory);
+    let mut optimized = Optimized { memory: memory.to_vec(), changes: Vec::new(), not_optimized: None };
+    let touched = match analyse(&decoded) {
+        Ok(touched) => touched,
+        Err(reason) => {
+            optimized.not_optimized = Some(reason);
+            return optimized;
+        }
+    };
+    let untouched = |d: &Decoded| (d.pos()..d.next()).all(|pos| !touched.contains(&pos));
+    let jumps: BTreeMap<Position, &Decoded> = decoded.iter()
+        .filter(|d| is_jump(d.instruction))
+        .map(|d| (d.pos(), d))
+        .collect();
+
+    let mut targets = BTreeSet::new();
+    for d in decoded.iter() {
+        let pos = d.pos() as usize;
+        match d.instruction {
+            Instruction::Add | Instruction::Multiply | Instruction::LessThan | Instruction::Equals if untouched(d) => {
+                if let Some((code, change)) = simplify(d) {
+                    if optimized.memory[pos..pos + code.len()] != code[..] {
+                        optimized.memory[pos..pos + code.len()].copy_from_slice(&code);
+                        optimized.changes.push(change);
+                    }
+                }
+            }
+            instruction if is_jump(instruction) => {
+                let from = match target(d) {
+                    Some(from) => from,
+                    None => continue,
+                };
+                let to = if untouched(d) && is_taken(d) != Some(false) { thread(from, &jumps) } else { from };
+                if to != from {
+                    optimized.memory[pos + 2] = to as Integer;
+                    optimized.changes.push(Change::Threaded { pos: d.pos(), from, to });
+                }
+                targets.insert(to);
+            }
+            _ => {}
+        }
+    }
+
+//    With every jump target known, a dead jump which nothing jumps to or falls through to can go.
+//    fall_through is the position after the previous instruction, if execution can continue there.
+    let mut fall_through: Option<Position> = None;
+    for d in decoded.iter() {
+        let reachable = d.pos() == 0 || targets.contains(&d.pos()) || fall_through == Some(d.pos());
+        let removed = !reachable && is_dead_jump(d) && untouched(d);
+        if removed {
+            let pos = d.pos() as usize;
+            for cell in optimized.memory[pos..d.next() as usize].iter_mut() {
+                *cell = 0;
+            }
+            optimized.changes.push(Change::RemovedDeadJump { pos: d.pos() });
+        }
+        fall_through = if !removed && falls_through(d) { Some(d.next()) } else { None };
+    }
+    optimized
+}
@@ -0,0 +1,99 @@
+//! What happened during a run, gathered while the machine runs so it doesn't need any tracing.
+//!
+//! ```
+//! use nine1::{HaltReason, Instruction, Program};
+//!
+//! let mut program = Program::new("109,5,203,-2,4,3,99", || Some(7), |_| {});
+//! let report = program.execute();
+//! assert_eq!(report.halt, HaltReason::Halt);
+//! assert_eq!((report.stats.cycles, report.stats.instructions[&Instruction::Input]), (4, 1));
+//! assert_eq!((report.stats.inputs, report.stats.outputs, report.stats.last_output), (1, 1, Some(7)));
+//! assert_eq!((report.stats.min_relative_base, report.stats.max_relative_base), (0, 5));
+//! assert_eq!(report.stats.highest_address, Some(6));
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{Instruction, Integer, IntcodeError, Position};
+
+/// Counts kept by a machine since it was created or restarted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Number of instructions executed, counting one that was stopped by an error
+    pub cycles: u64,
+    /// Number of times each built-in instruction was executed
+    pub instructions: BTreeMap<Instruction, u64>,
+    /// Number of times each extension opcode was executed
+    pub extensions: BTreeMap<u8, u64>,
+    /// Highest address read from or written to, including where instructions were read from
+    pub highest_address: Option<Position>,
+    pub min_relative_base: Integer,
+    pub max_relative_base: Integer,
+    /// Number of input values read
+    pub inputs: u64,
+    /// Number of values output
+    pub outputs: u64,
+    pub last_output: Option<Integer>,
+}
+
+impl Stats {
+    /// Stats for a run starting with the given relative base
+    pub(crate) fn starting_at(relative_base: Integer) -> Self {
+        Self { min_relative_base: relative_base, max_relative_base: relative_base, ..Self::default() }
+    }
+
+    pub(crate) fn record_relative_base(&mut self, relative_base: Integer) {
+        self.min_relative_base = self.min_relative_base.min(relative_base);
+        self.max_relative_base = self.max_relative_base.max(relative_base);
+    }
+}
+
+/// Why a run stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum HaltReason {
+    /// Reached a halt instruction
+    Halt,
+    /// An extension halted it with the exit code
+    Extension(Integer),
+    Error(IntcodeError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub stats: Stats,
+    pub halt: HaltReason,
+}
+
+impl RunReport {
+    /// Last value output by the run, or the error it stopped with
+    pub fn result(&self) -> Result<Option<Integer>, IntcodeError> {
+        match &self.halt {
+            HaltReason::Error(err) => Err(err.clone()),
+            HaltReason::Halt | HaltReason::Extension(_) => Ok(self.stats.last_output),
+        }
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stats = &self.stats;
+        match &self.halt {
+            HaltReason::Halt => writeln!(f, "Halted")?,
+            HaltReason::Extension(code) => writeln!(f, "Halted by extension with code {}", code)?,
+            HaltReason::Error(err) => writeln!(f, "Stopped by error: {}", err)?,
+        }
+        writeln!(f, "Cycles: {}", stats.cycles)?;
+        for (instruction, count) in stats.instructions.iter() {
+            writeln!(f, "  {:?}: {}", instruction, count)?;
+        }
+        for (opcode, count) in stats.extensions.iter() {
+            writeln!(f, "  Extension {}: {}", opcode, count)?;
+        }
+        match stats.highest_address {
+            Some(address) => writeln!(f, "Highest address: {}", address)?,
+            None => writeln!(f, "Highest address: none")?,
+        }
+        writeln!(f, "Relative base: {} to {}", stats.min_relative_base, stats.max_relative_base)?;
+        write!(f, "Inputs: {}, outputs: {}", stats.inputs, stats.outputs)
+    }
+}
@@ -0,0 +1,187 @@
+//! Hull-painting robot driven by an intcode brain. The robot's camera gives the brain the colour
+//! of the panel under it as input, and the brain answers with pairs of outputs: the colour to
+//! paint the panel and which way to turn (0 for left, 1 for right) before moving forward a panel.
+//!
+//! ```
+//! use nine1::robot::{self, Colour};
+//!
+//! // Paints each panel white and turns right, going round a 2x2 square
+//! let robot = robot::run("3,100,104,1,104,1,1105,1,0", Colour::Black, Some(4)).unwrap();
+//! assert_eq!(robot.painted(), 4);
+//! assert_eq!(robot.render(), "██\n██");
+//! ```
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Integer, IntcodeError, Program};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    Black = 0,
+    White = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    fn turn_right(self) -> Self {
+        use Direction::*;
+        match self {
+            Up => Right,
+            Right => Down,
+            Down => Left,
+            Left => Up,
+        }
+    }
+
+    fn turn_left(self) -> Self {
+        self.turn_right().turn_right().turn_right()
+    }
+
+    /// Change in x and y for a step forward, with y growing downwards
+    fn step(self) -> (Integer, Integer) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+        }
+    }
+}
+
+/// Robot on an unbounded grid of panels, which are all black until painted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Robot {
+    pos: (Integer, Integer),
+    direction: Direction,
+    /// Colour of the starting panel and of every panel painted since
+    panels: BTreeMap<(Integer, Integer), Colour>,
+    /// Panels the brain has painted at least once
+    painted: HashSet<(Integer, Integer)>,
+    /// Colour output by the brain which is waiting for its turn direction
+    paint: Option<Integer>,
+    moves: usize,
+}
+
+impl Robot {
+    /// Robot facing up on a panel of the given colour
+    pub fn new(start: Colour) -> Self {
+        let mut panels = BTreeMap::new();
+        if start == Colour::White {
+            panels.insert((0, 0), start);
+        }
+        Self { pos: (0, 0), direction: Direction::Up, panels, painted: HashSet::new(), paint: None, moves: 0 }
+    }
+
+    pub fn pos(&self) -> (Integer, Integer) {
+        self.pos
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Colour of the panel at pos
+    pub fn colour(&self, pos: (Integer, Integer)) -> Colour {
+        self.panels.get(&pos).cloned().unwrap_or(Colour::Black)
+    }
+
+    /// What the camera sees, which is the colour of the panel under the robot
+    pub fn camera(&self) -> Colour {
+        self.colour(self.pos)
+    }
+
+    /// Number of panels painted at least once. A white starting panel only counts once the brain
+    /// paints it.
+    pub fn painted(&self) -> usize {
+        self.painted.len()
+    }
+
+    /// Number of times the robot has moved
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Handle an output from the brain: a colour to paint, or the turn which follows it
+    pub fn push(&mut self, val: Integer) {
+        let paint = match self.paint.take() {
+            Some(paint) => paint,
+            None => {
+                self.paint = Some(val);
+                return;
+            }
+        };
+        let colour = match paint {
+            0 => Some(Colour::Black),
+            1 => Some(Colour::White),
+            _ => {
+                log::warn!("Ignoring unknown colour {} at {:?}", paint, self.pos);
+                None
+            }
+        };
+        if let Some(colour) = colour {
+            self.panels.insert(self.pos, colour);
+            self.painted.insert(self.pos);
+        }
+        self.direction = match val {
+            0 => self.direction.turn_left(),
+            1 => self.direction.turn_right(),
+            _ => {
+                log::warn!("Ignoring unknown turn {} at {:?}", val, self.pos);
+                self.direction
+            }
+        };
+        let (dx, dy) = self.direction.step();
+        self.pos = (self.pos.0 + dx, self.pos.1 + dy);
+        self.moves += 1;
+    }
+
+    /// Painted panels with the same block characters as the decoded space images from day 8.
+    /// Panels that were never painted are left blank.
+    pub fn render(&self) -> String {
+        if self.panels.is_empty() {
+            return String::new();
+        }
+        let min_x = self.panels.keys().map(|&(x, _)| x).min().unwrap();
+        let max_x = self.panels.keys().map(|&(x, _)| x).max().unwrap();
+        let min_y = self.panels.keys().map(|&(_, y)| y).min().unwrap();
+        let max_y = self.panels.keys().map(|&(_, y)| y).max().unwrap();
+        let rows = (min_y..=max_y)
+            .map(|y| (min_x..=max_x).map(|x| match self.panels.get(&(x, y)) {
+                Some(&colour) => colour as u32,
+//                Transparent in a space image
+                None => 2,
+            }).collect())
+            .collect::<Vec<Vec<u32>>>();
+        eight2::render(&rows)
+    }
+}
+
+/// Run the brain until it halts, or until the robot has moved max_moves times if given, e.g. for
+/// brains which never stop
+pub fn run(intcode: &str, start: Colour, max_moves: Option<usize>) -> Result<Robot, IntcodeError> {
+    let robot = RefCell::new(Robot::new(start));
+    let mut program = Program::new(
+        intcode,
+        || {
+            let robot = robot.borrow();
+            if max_moves.is_some_and(|max| robot.moves() >= max) {
+                return None;
+            }
+            Some(robot.camera() as Integer)
+        },
+        |val| robot.borrow_mut().push(val));
+    match program.execute().result() {
+        Ok(_) => {}
+//        Stopped by running out of moves
+        Err(IntcodeError::InputExhausted { .. }) if max_moves.is_some() => {}
+        Err(err) => return Err(err),
+    }
+    Ok(robot.into_inner())
+}
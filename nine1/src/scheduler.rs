@@ -0,0 +1,212 @@
+//! Scheduler which runs several machines on one thread, passing values between them through
+//! explicit queues, e.g. for the amplifier feedback loop from day 7. Every machine has an inbox
+//! which its input instructions read from, and its outputs go to the inbox of the machine it's
+//! connected to, if any.
+//!
+//! ```
+//! use nine1::scheduler::{Policy, Scheduler};
+//! use nine1::Machine;
+//!
+//! // Adds its phase to each value it reads, 3 times
+//! let amplifier = vec![3, 30, 3, 31, 1, 30, 31, 31, 4, 31, 1001, 32, 1, 32, 1007, 32, 3, 33, 1005, 33, 2, 99];
+//! let mut scheduler = Scheduler::new(Policy::EventOrder);
+//! let first = scheduler.add(Machine::new(amplifier.clone()));
+//! let second = scheduler.add(Machine::new(amplifier));
+//! scheduler.connect(first, second);
+//! scheduler.connect(second, first);
+//! scheduler.send(first, 1);
+//! scheduler.send(second, 10);
+//! scheduler.send(first, 0);
+//!
+//! let finished = scheduler.run().unwrap();
+//! assert_eq!((finished.last_halted, finished.final_output), (second, Some(33)));
+//! ```
+//!
+//! A machine only stops running when it halts or wants input which isn't in its inbox yet. If
+//! every machine which hasn't halted is waiting like that, none of them can ever continue and
+//! `run` reports a deadlock.
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Integer, IntcodeError, Machine, State};
+
+/// Index of a machine in its scheduler, in the order they were added
+pub type MachineId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Take the machines in turn, running each one until it halts or waits for input
+    RoundRobin,
+    /// Run a machine until it outputs a value, then let the machine that value was sent to run
+    /// next, so values are handled in the order they were sent
+    EventOrder,
+}
+
+/// How the run ended when every machine halted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Finished {
+    pub last_halted: MachineId,
+    /// Last value output by the machine which halted last
+    pub final_output: Option<Integer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchedulerError {
+    /// The machine stopped with an error
+    Machine { id: MachineId, error: IntcodeError },
+    /// Every machine which hasn't halted is waiting for input that nothing will send
+    Deadlock { waiting: Vec<MachineId> },
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchedulerError::Machine { id, error } => write!(f, "Machine {}: {}", id, error),
+            SchedulerError::Deadlock { waiting } => write!(f, "Deadlock with machines {:?} waiting for input", waiting),
+        }
+    }
+}
+
+impl Error for SchedulerError {}
+
+struct Slot {
+    machine: Machine,
+    inbox: VecDeque<Integer>,
+    target: Option<MachineId>,
+    outputs: Vec<Integer>,
+    /// Stopped at an input instruction with an empty inbox
+    waiting: bool,
+}
+
+impl Slot {
+    fn is_runnable(&self) -> bool {
+        !self.machine.is_halted() && (!self.waiting || !self.inbox.is_empty())
+    }
+}
+
+pub struct Scheduler {
+    slots: Vec<Slot>,
+    policy: Policy,
+    last_halted: Option<MachineId>,
+}
+
+impl Scheduler {
+    pub fn new(policy: Policy) -> Self {
+        Self { slots: Vec::new(), policy, last_halted: None }
+    }
+
+    pub fn add(&mut self, machine: Machine) -> MachineId {
+        if machine.is_halted() {
+            self.last_halted = Some(self.slots.len());
+        }
+        self.slots.push(Slot { machine, inbox: VecDeque::new(), target: None, outputs: Vec::new(), waiting: false });
+        self.slots.len() - 1
+    }
+
+    /// Send everything the first machine outputs from now on to the second one's inbox
+    pub fn connect(&mut self, from: MachineId, to: MachineId) {
+        assert!(to < self.slots.len(), "No machine {} to connect to", to);
+        self.slots[from].target = Some(to);
+    }
+
+    /// Add a value to the machine's inbox, e.g. its settings before the run starts
+    pub fn send(&mut self, to: MachineId, val: Integer) {
+        self.slots[to].inbox.push_back(val);
+    }
+
+    pub fn machine(&self, id: MachineId) -> &Machine {
+        &self.slots[id].machine
+    }
+
+    /// Values which have been sent to the machine but not read yet
+    pub fn inbox(&self, id: MachineId) -> impl Iterator<Item = Integer> + '_ {
+        self.slots[id].inbox.iter().cloned()
+    }
+
+    /// Everything the machine has output so far, whether or not it was sent anywhere
+    pub fn outputs(&self, id: MachineId) -> &[Integer] {
+        &self.slots[id].outputs
+    }
+
+    /// Run the machine until it halts or waits for input, or with `stop_at_output` until it
+    /// outputs a value. Returns the machine its output went to, if there was one.
+    fn run_machine(&mut self, id: MachineId, stop_at_output: bool) -> Result<Option<MachineId>, SchedulerError> {
+        loop {
+            let slot = &mut self.slots[id];
+            match slot.machine.resume() {
+                Ok(State::Output(val)) => {
+                    slot.outputs.push(val);
+                    let target = slot.target;
+                    if let Some(target) = target {
+                        log::debug!("Machine {} sent {} to machine {}", id, val, target);
+                        self.slots[target].inbox.push_back(val);
+                    }
+                    if stop_at_output {
+                        return Ok(target);
+                    }
+                }
+                Ok(State::NeedsInput) => match slot.inbox.pop_front() {
+                    Some(input) => {
+                        slot.waiting = false;
+                        slot.machine.push_input(input);
+                    }
+                    None => {
+                        slot.waiting = true;
+                        return Ok(None);
+                    }
+                },
+                Ok(State::Halted) => {
+                    log::debug!("Machine {} halted", id);
+                    self.last_halted = Some(id);
+                    return Ok(None);
+                }
+                Err(error) => return Err(SchedulerError::Machine { id, error }),
+            }
+        }
+    }
+
+    fn run_round_robin(&mut self) -> Result<(), SchedulerError> {
+        while self.slots.iter().any(Slot::is_runnable) {
+            for id in 0..self.slots.len() {
+                if self.slots[id].is_runnable() {
+                    self.run_machine(id, false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_in_event_order(&mut self) -> Result<(), SchedulerError> {
+        let mut ready: VecDeque<MachineId> = (0..self.slots.len()).collect();
+        while let Some(id) = ready.pop_front() {
+            if !self.slots[id].is_runnable() {
+                continue;
+            }
+            if let Some(target) = self.run_machine(id, true)? {
+                if !ready.contains(&target) {
+                    ready.push_back(target);
+                }
+            }
+            if self.slots[id].is_runnable() && !ready.contains(&id) {
+                ready.push_back(id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run until every machine has halted. Panics if there are no machines.
+    pub fn run(&mut self) -> Result<Finished, SchedulerError> {
+        assert!(!self.slots.is_empty(), "No machines to run");
+        match self.policy {
+            Policy::RoundRobin => self.run_round_robin()?,
+            Policy::EventOrder => self.run_in_event_order()?,
+        }
+        let waiting = (0..self.slots.len()).filter(|&id| !self.slots[id].machine.is_halted()).collect::<Vec<_>>();
+        if !waiting.is_empty() {
+            return Err(SchedulerError::Deadlock { waiting });
+        }
+        let last_halted = self.last_halted.expect("Some machine should have halted");
+        Ok(Finished { last_halted, final_output: self.slots[last_halted].outputs.last().cloned() })
+    }
+}
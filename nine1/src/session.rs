@@ -0,0 +1,194 @@
+//! Recording the I/O of a run so it can be replayed exactly, e.g. to reproduce a bug in an
+//! interactive program.
+//!
+//! Sessions are saved as text with one event per line:
+//!
+//! ```text
+//! in <cycle> <pos> <value>
+//! out <cycle> <pos> <value>
+//! halt <cycle> <pos>
+//! ```
+//!
+//! where pos is the position of the instruction which consumed the input, produced the output
+//! or halted the program.
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{Integer, IntcodeError, Position, Program};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Input { cycle: u64, pos: Position, value: Integer },
+    Output { cycle: u64, pos: Position, value: Integer },
+    Halt { cycle: u64, pos: Position },
+}
+
+impl Event {
+    pub fn cycle(&self) -> u64 {
+        match self {
+            Event::Input { cycle, .. } | Event::Output { cycle, .. } | Event::Halt { cycle, .. } => *cycle,
+        }
+    }
+
+    pub fn pos(&self) -> Position {
+        match self {
+            Event::Input { pos, .. } | Event::Output { pos, .. } | Event::Halt { pos, .. } => *pos,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Input { cycle, pos, value } => write!(f, "in {} {} {}", cycle, pos, value),
+            Event::Output { cycle, pos, value } => write!(f, "out {} {} {}", cycle, pos, value),
+            Event::Halt { cycle, pos } => write!(f, "halt {} {}", cycle, pos),
+        }
+    }
+}
+
+fn parse_event(line: &str) -> Option<Event> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    let cycle = fields.get(1)?.parse().ok()?;
+    let pos = fields.get(2)?.parse().ok()?;
+    let value = || fields.get(3)?.parse().ok();
+    match (fields[0], fields.len()) {
+        ("in", 4) => Some(Event::Input { cycle, pos, value: value()? }),
+        ("out", 4) => Some(Event::Output { cycle, pos, value: value()? }),
+        ("halt", 3) => Some(Event::Halt { cycle, pos }),
+        _ => None,
+    }
+}
+
+/// Everything a program read and wrote, in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub events: Vec<Event>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Input values in the order they were consumed
+    pub fn inputs(&self) -> impl Iterator<Item = Integer> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Input { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for event in self.events.iter() {
+            writeln!(writer, "{}", event)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut session = Session::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = parse_event(&line).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData, format!("Invalid session event: {}", line)))?;
+            session.record(event);
+        }
+        Ok(session)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// First point where a replay didn't do what was recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub cycle: u64,
+    pub pos: Position,
+    /// Recorded event, or None if the replay did something which wasn't recorded
+    pub expected: Option<Event>,
+    /// Event from the replay, or None if it stopped before getting there
+    pub actual: Option<Event>,
+    /// Why the replay stopped, if it stopped with an error before the expected event
+    pub error: Option<IntcodeError>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Diverged at cycle {}, pos {}: ", self.cycle, self.pos)?;
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => write!(f, "expected `{}` but got `{}`", expected, actual),
+            (Some(expected), None) => write!(f, "expected `{}` but the program stopped", expected),
+            (None, Some(actual)) => write!(f, "got unexpected `{}`", actual),
+            (None, None) => write!(f, "no events"),
+        }?;
+        if let Some(err) = &self.error {
+            write!(f, " ({})", err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the program again, feeding it the recorded inputs, and check that it reads, writes and
+/// halts at exactly the same cycles and positions as before
+pub fn replay(memory: &[Integer], session: &Session) -> Result<(), Divergence> {
+    let inputs = RefCell::new(session.inputs());
+//    Running past the last recorded event means the replay went somewhere else
+    let last_cycle = session.events.last().map_or(0, |event| event.cycle());
+    let mut program = Program::from_memory(memory.to_vec(), || inputs.borrow_mut().next(), |_| {})
+        .with_recording()
+        .with_cycle_limit(last_cycle);
+    let result = program.execute().result();
+    let actual = program.session().expect("Recording was enabled").events.clone();
+
+    for (i, expected) in session.events.iter().enumerate() {
+        match actual.get(i) {
+            Some(event) if event == expected => continue,
+            Some(event) => return Err(Divergence {
+                cycle: event.cycle(),
+                pos: event.pos(),
+                expected: Some(*expected),
+                actual: Some(*event),
+                error: None,
+            }),
+            None => {
+                let err = result.err();
+                return Err(Divergence {
+                    cycle: program.cycles(),
+                    pos: err.as_ref().and_then(|err| err.pos()).unwrap_or(expected.pos()),
+                    expected: Some(*expected),
+                    actual: None,
+                    error: err,
+                });
+            }
+        }
+    }
+    match actual.get(session.events.len()) {
+        Some(event) => Err(Divergence {
+            cycle: event.cycle(),
+            pos: event.pos(),
+            expected: None,
+            actual: Some(*event),
+            error: None,
+        }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+
+use nine1::arcade::{self, free_play, ArcadeError, FollowBall, Joystick, Screen, Tile};
+use nine1::compiler::compile;
+use nine1::loader::LoadError;
+use nine1::IntcodeError;
+
+/// Catch game: balls fall one at a time from the top and each one caught with the paddle knocks
+/// out the block above the column it fell in, and scores a point
+const CATCH: &str = "
+    fn draw(x, y, tile) {
+        output(x);
+        output(y);
+        output(tile);
+    }
+
+    let width = 10;
+    let height = 8;
+    let x = 0;
+    while x < width {
+        draw(x, 0, 1);
+        x = x + 1;
+    }
+    let y = 1;
+    while y < height {
+        draw(0, y, 1);
+        draw(width - 1, y, 1);
+        y = y + 1;
+    }
+    x = 1;
+    while x < width - 1 {
+        draw(x, 1, 2);
+        x = x + 1;
+    }
+    let paddle = 4;
+    draw(paddle, height - 1, 3);
+    let score = 0;
+    draw(-1, 0, score);
+
+    let ball = 1;
+    let round = 0;
+    while round < 8 {
+        let ball_y = 2;
+        while ball_y < height - 1 {
+            draw(ball, ball_y, 4);
+            let joystick = input();
+            let moved = paddle + joystick;
+            if moved > 0 && moved < width - 1 {
+                draw(paddle, height - 1, 0);
+                paddle = moved;
+                draw(paddle, height - 1, 3);
+            }
+            draw(ball, ball_y, 0);
+            ball_y = ball_y + 1;
+        }
+        if paddle == ball {
+            score = score + 1;
+            draw(-1, 0, score);
+            draw(ball, 1, 0);
+        }
+        ball = ball + 3;
+        if ball > 8 {
+            ball = ball - 8;
+        }
+        round = round + 1;
+    }
+";
+
+fn catch() -> String {
+    compile(CATCH).unwrap()
+}
+
+#[test]
+fn draws_the_board() {
+    let screen = arcade::run_headless(&catch(), |_: &Screen| Joystick::Neutral).unwrap();
+    assert_eq!(screen.count(Tile::Wall), 24);
+    assert_eq!(screen.count(Tile::Paddle), 1);
+    assert_eq!(screen.paddle(), Some((4, 7)));
+    assert_eq!(screen.ball(), None);
+}
+
+#[test]
+fn standing_still_catches_one_ball() {
+    let screen = arcade::run_headless(&catch(), |_: &Screen| Joystick::Neutral).unwrap();
+    assert_eq!(screen.count(Tile::Block), 7);
+    assert_eq!(screen.tile(4, 1), Tile::Empty);
+    assert_eq!(screen.score(), 1);
+}
+
+#[test]
+fn following_the_ball_clears_every_block() {
+    let screen = arcade::run_headless(&catch(), FollowBall).unwrap();
+    assert_eq!(screen.count(Tile::Block), 0);
+    assert_eq!(screen.count(Tile::Wall), 24);
+    assert_eq!(screen.score(), 8);
+}
+
+#[test]
+fn frames_are_shown_before_each_input_and_at_the_end() {
+    let frames = RefCell::new(Vec::new());
+    let screen = arcade::run(&catch(), FollowBall, |screen| frames.borrow_mut().push(screen.clone())).unwrap();
+    let frames = frames.into_inner();
+//    5 inputs for each of the 8 balls
+    assert_eq!(frames.len(), 41);
+    assert_eq!(frames[0].ball(), Some((1, 2)));
+    assert_eq!(frames[40], screen);
+}
+
+#[test]
+fn render() {
+    let screen = arcade::run_headless(&catch(), |_: &Screen| Joystick::Left).unwrap();
+    assert_eq!(screen.render(), [
+        "Score: 1",
+        "██████████",
+        "█ ▒▒▒▒▒▒▒█",
+        "█        █",
+        "█        █",
+        "█        █",
+        "█        █",
+        "█        █",
+        "█▔       █",
+    ].join("\n"));
+}
+
+#[test]
+fn unknown_tiles_and_partial_triples_are_ignored() {
+    let mut screen = Screen::new();
+    for &val in &[1, 1, 7, 2, 2, 4, 3, 3] {
+        screen.push(val);
+    }
+    assert_eq!(screen.tile(1, 1), Tile::Empty);
+    assert_eq!(screen.ball(), Some((2, 2)));
+    assert_eq!(screen.tile(3, 3), Tile::Empty);
+    screen.push(2);
+    assert_eq!(screen.tile(3, 3), Tile::Block);
+}
+
+#[test]
+fn free_play_puts_in_quarters() {
+    assert_eq!(free_play("1,0,0,0,99\n").unwrap(), "2,0,0,0,99");
+    assert!(matches!(free_play("1,x"), Err(LoadError::InvalidValue { index: 1, .. })));
+}
+
+#[test]
+fn errors() {
+    let err = arcade::run_headless("104,0,x", FollowBall).unwrap_err();
+    assert!(matches!(err, ArcadeError::Load(LoadError::InvalidValue { index: 2, .. })), "{:?}", err);
+    let err = arcade::run_headless("104,0,3,-1", FollowBall).unwrap_err();
+    assert!(matches!(err, ArcadeError::Intcode(IntcodeError::NegativeAddress { pos: 3, address: -1 })), "{:?}", err);
+    assert_eq!(err.to_string(), "Param at pos 3 refers to negative address -1");
+}
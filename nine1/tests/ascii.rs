@@ -0,0 +1,74 @@
+use nine1::ascii::{encode_line, read_script, run_scripted, AsciiOutput, LineDecoder};
+use nine1::IntcodeError;
+
+/// Prompts with "> " and echoes each command, following it with 1000 plus its length. Stops at
+/// a '.' and says bye without a newline.
+const ECHO: &str = "104,62,104,32,3,100,1008,100,46,102,1005,102,44,1008,100,10,102,1005,102,29,4,100,\
+    1001,101,1,101,1105,1,4,1001,101,1000,102,4,102,104,10,1101,0,0,101,1105,1,0,104,98,104,121,104,101,99";
+
+fn line(text: &str) -> AsciiOutput {
+    AsciiOutput::Line(text.to_owned())
+}
+
+fn commands(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn encodes_lines() {
+    assert_eq!(encode_line("Hi"), vec![72, 105, 10]);
+    assert_eq!(encode_line(""), vec![10]);
+}
+
+#[test]
+fn splits_output_into_lines() {
+    let mut decoder = LineDecoder::new();
+    let outputs: Vec<_> = "ab\n\nc\n".chars().filter_map(|c| decoder.push(c as i64)).collect();
+    assert_eq!(outputs, vec![line("ab"), line(""), line("c")]);
+    assert_eq!(decoder.finish(), None);
+}
+
+#[test]
+fn passes_large_values_through() {
+    let mut decoder = LineDecoder::new();
+    assert_eq!(decoder.push('a' as i64), None);
+    assert_eq!(decoder.push(127), None);
+    assert_eq!(decoder.push(128), Some(AsciiOutput::Value(128)));
+    assert_eq!(decoder.push(19_349_722), Some(AsciiOutput::Value(19_349_722)));
+    assert_eq!(decoder.push(10), Some(line("a\u{7f}")));
+}
+
+#[test]
+fn flushes_final_line() {
+    let mut decoder = LineDecoder::new();
+    decoder.push('>' as i64);
+    decoder.push(' ' as i64);
+    assert_eq!(decoder.finish(), Some(line("> ")));
+    assert_eq!(decoder.finish(), None);
+}
+
+#[test]
+fn runs_scripted_commands() {
+    assert_eq!(run_scripted(ECHO, &commands(&["ab", "x."])), Ok(vec![
+        line("> "),
+        AsciiOutput::Value(1002),
+        line("ab"),
+        line("> "),
+        line("xbye"),
+    ]));
+}
+
+#[test]
+fn running_out_of_commands() {
+    let result = run_scripted(ECHO, &commands(&["ab"]));
+    assert!(matches!(result, Err(IntcodeError::InputExhausted { .. })), "{:?}", result);
+}
+
+#[test]
+fn reads_scripts() {
+    let path = std::env::temp_dir().join(format!("nine1-script-{}.txt", std::process::id()));
+    std::fs::write(&path, "north\ntake mug\n").unwrap();
+    let script = read_script(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(script.unwrap(), commands(&["north", "take mug"]));
+}
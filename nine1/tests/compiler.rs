@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+
+use nine1::compiler::compile;
+use nine1::{IntcodeError, Integer, Program};
+
+fn run(source: &str, inputs: &[Integer]) -> Result<Vec<Integer>, IntcodeError> {
+    let intcode = compile(source).unwrap_or_else(|err| panic!("Failed to compile: {}", err));
+    let inputs = RefCell::new(inputs.iter().cloned());
+    let outputs = RefCell::new(Vec::new());
+    let mut program = Program::new(&intcode, || inputs.borrow_mut().next(), |val| outputs.borrow_mut().push(val))
+        .with_cycle_limit(10_000_000);
+    program.execute().result()?;
+    Ok(outputs.into_inner())
+}
+
+fn compile_error(source: &str) -> (usize, String) {
+    let err = compile(source).unwrap_err();
+    (err.line, err.message)
+}
+
+#[test]
+fn empty_program_halts() {
+    assert_eq!(run("", &[]).unwrap(), vec![]);
+    assert_eq!(run("// Nothing here", &[]).unwrap(), vec![]);
+}
+
+#[test]
+fn echo() {
+    assert_eq!(run("output(input());", &[42]).unwrap(), vec![42]);
+    assert_eq!(run("output(input()); output(input());", &[1, 2]).unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn arithmetic_and_precedence() {
+    assert_eq!(run("output(1 + 2 * 3);", &[]).unwrap(), vec![7]);
+    assert_eq!(run("output((1 + 2) * 3);", &[]).unwrap(), vec![9]);
+    assert_eq!(run("output(10 - 3 - 2);", &[]).unwrap(), vec![5]);
+    assert_eq!(run("output(-input() * 2);", &[4]).unwrap(), vec![-8]);
+    assert_eq!(run("output(1125899906842624 * 2);", &[]).unwrap(), vec![2251799813685248]);
+}
+
+#[test]
+fn comparisons() {
+    let source = "
+        let a = input();
+        let b = input();
+        output(a < b);
+        output(a <= b);
+        output(a > b);
+        output(a >= b);
+        output(a == b);
+        output(a != b);
+    ";
+    assert_eq!(run(source, &[1, 2]).unwrap(), vec![1, 1, 0, 0, 0, 1]);
+    assert_eq!(run(source, &[2, 2]).unwrap(), vec![0, 1, 0, 1, 1, 0]);
+    assert_eq!(run(source, &[3, 2]).unwrap(), vec![0, 0, 1, 1, 0, 1]);
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let source = "
+        fn noisy(x) {
+            output(100 + x);
+            return x;
+        }
+        output(noisy(0) && noisy(1));
+        output(noisy(2) && noisy(3));
+        output(noisy(0) || noisy(4));
+        output(noisy(5) || noisy(6));
+        output(!0);
+        output(!7);
+    ";
+    assert_eq!(run(source, &[]).unwrap(), vec![100, 0, 102, 103, 1, 100, 104, 1, 105, 1, 1, 0]);
+}
+
+#[test]
+fn if_else_chain() {
+    let source = "
+        let x = input();
+        if x < 0 {
+            output(-1);
+        } else if x == 0 {
+            output(0);
+        } else {
+            output(1);
+        }
+    ";
+    assert_eq!(run(source, &[-5]).unwrap(), vec![-1]);
+    assert_eq!(run(source, &[0]).unwrap(), vec![0]);
+    assert_eq!(run(source, &[5]).unwrap(), vec![1]);
+}
+
+#[test]
+fn while_loop_sums_inputs_until_zero() {
+    let source = "
+        let total = 0;
+        let x = input();
+        while x != 0 {
+            total = total + x;
+            x = input();
+        }
+        output(total);
+    ";
+    assert_eq!(run(source, &[1, 2, 3, 0]).unwrap(), vec![6]);
+    assert_eq!(run(source, &[0]).unwrap(), vec![0]);
+}
+
+#[test]
+fn block_scoping() {
+    let source = "
+        let x = 1;
+        if 1 {
+            let x = 2;
+            output(x);
+        }
+        output(x);
+    ";
+    assert_eq!(run(source, &[]).unwrap(), vec![2, 1]);
+}
+
+#[test]
+fn recursive_fibonacci() {
+    let source = "
+        fn fib(n) {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+        output(fib(input()));
+    ";
+    assert_eq!(run(source, &[0]).unwrap(), vec![0]);
+    assert_eq!(run(source, &[1]).unwrap(), vec![1]);
+    assert_eq!(run(source, &[20]).unwrap(), vec![6765]);
+}
+
+#[test]
+fn boost_recursion() {
+//    The recursive function at the end of BOOST, which gives its answer for input 2
+    let source = "
+        fn f(n) {
+            if n < 3 {
+                return n;
+            }
+            let a = f(n - 1);
+            return a + f(n - 3);
+        }
+        output(f(input()) + 49329);
+    ";
+    assert_eq!(run(source, &[27]).unwrap(), vec![70634]);
+}
+
+#[test]
+fn functions_with_several_params_and_locals() {
+    let source = "
+        fn power(base, exp) {
+            let result = 1;
+            while exp > 0 {
+                result = result * base;
+                exp = exp - 1;
+            }
+            return result;
+        }
+        fn max3(a, b, c) {
+            let m = a;
+            if b > m { m = b; }
+            if c > m { m = c; }
+            return m;
+        }
+        output(power(input(), input()));
+        output(max3(power(2, 3), 5, power(3, 2)));
+    ";
+    assert_eq!(run(source, &[3, 4]).unwrap(), vec![81, 9]);
+}
+
+#[test]
+fn mutual_recursion_and_call_before_definition() {
+    let source = "
+        output(is_even(input()));
+        fn is_even(n) {
+            if n == 0 { return 1; }
+            return is_odd(n - 1);
+        }
+        fn is_odd(n) {
+            if n == 0 { return 0; }
+            return is_even(n - 1);
+        }
+    ";
+    assert_eq!(run(source, &[10]).unwrap(), vec![1]);
+    assert_eq!(run(source, &[7]).unwrap(), vec![0]);
+}
+
+#[test]
+fn functions_without_params_or_return_value() {
+    let source = "
+        fn greet() {
+            output(72);
+            output(105);
+        }
+        fn nothing() {
+            return;
+        }
+        greet();
+        output(nothing());
+        output(greet());
+    ";
+    assert_eq!(run(source, &[]).unwrap(), vec![72, 105, 0, 72, 105, 0]);
+}
+
+#[test]
+fn nested_calls_in_arguments() {
+    let source = "
+        fn add(a, b) { return a + b; }
+        output(add(add(1, 2), add(add(3, 4), 5)));
+    ";
+    assert_eq!(run(source, &[]).unwrap(), vec![15]);
+}
+
+#[test]
+fn input_runs_out() {
+    assert_eq!(run("output(input());", &[]), Err(IntcodeError::InputExhausted { pos: 4 }));
+}
+
+#[test]
+fn syntax_errors() {
+    assert_eq!(compile_error("output(1)"), (1, "Expected `;` but found end of input".to_owned()));
+    assert_eq!(compile_error("let x = 1;\nlet = 2;"), (2, "Expected a name but found =".to_owned()));
+    assert_eq!(compile_error("\n\noutput(1 $ 2);"), (3, "Unexpected character '$'".to_owned()));
+    assert_eq!(compile_error("if 1 { output(1);"), (1, "Expected `}` but found end of input".to_owned()));
+    assert_eq!(compile_error("let while = 1;"), (1, "Expected a name but found while".to_owned()));
+    assert_eq!(compile_error("output(99999999999999999999);").1, "Number 99999999999999999999 is too large");
+}
+
+#[test]
+fn semantic_errors() {
+    assert_eq!(compile_error("output(x);"), (1, "Unknown variable x".to_owned()));
+    assert_eq!(compile_error("if 1 { let x = 1; }\nx = 2;"), (2, "Unknown variable x".to_owned()));
+    assert_eq!(compile_error("let x = 1;\nlet x = 2;"), (2, "Variable x is already declared".to_owned()));
+    assert_eq!(compile_error("f();"), (1, "Unknown function f".to_owned()));
+    assert_eq!(compile_error("fn f(a) { return a; }\nf(1, 2);"), (2, "f takes 1 arguments but was given 2".to_owned()));
+    assert_eq!(compile_error("fn f() {}\nfn f() {}"), (2, "Function f is already defined".to_owned()));
+    assert_eq!(compile_error("return 1;"), (1, "return outside of a function".to_owned()));
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+
+use nine1::{BranchCounts, Coverage, Extensions, Integer, Program};
+
+/// Outputs 1, after outputting 0 first if its input is 0
+const JUMP_IF_TRUE: &str = "3,11,1005,11,7,104,0,104,1,99,0,0";
+/// Outputs 0 if its input is 0, else 1
+const JUMP_IF_FALSE: &str = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+/// Amplifier from the first day 7 example, which adds its phase to 10 times its input
+const AMPLIFIER: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+
+fn coverage(intcode: &str, inputs: &[Integer]) -> Coverage {
+    let inputs = RefCell::new(inputs.iter().cloned());
+    let mut program = Program::new(intcode, || inputs.borrow_mut().next(), |_| {}).with_coverage();
+    program.execute().result().unwrap();
+    program.coverage().unwrap().clone()
+}
+
+fn parse(intcode: &str) -> Vec<Integer> {
+    intcode.split(',').map(|x| x.parse().unwrap()).collect()
+}
+
+fn permutations(values: Vec<Integer>) -> Vec<Vec<Integer>> {
+    if values.len() <= 1 {
+        return vec![values];
+    }
+    (0..values.len()).flat_map(|i| {
+        let mut rest = values.clone();
+        let first = rest.remove(i);
+        permutations(rest).into_iter().map(move |mut perm| {
+            perm.insert(0, first);
+            perm
+        })
+    }).collect()
+}
+
+#[test]
+fn jump_if_true_branches() {
+    let taken = coverage(JUMP_IF_TRUE, &[1]);
+    assert_eq!(taken.branch(2), Some(BranchCounts { taken: 1, not_taken: 0 }));
+    assert!(!taken.executed(5));
+    assert!(taken.executed(7));
+
+    let not_taken = coverage(JUMP_IF_TRUE, &[0]);
+    assert_eq!(not_taken.branch(2), Some(BranchCounts { taken: 0, not_taken: 1 }));
+    assert_eq!((not_taken.hits(5), not_taken.hits(7)), (1, 1));
+    assert_eq!(not_taken.branch(0), None);
+}
+
+#[test]
+fn jump_if_false_branches() {
+    assert_eq!(coverage(JUMP_IF_FALSE, &[0]).branch(2), Some(BranchCounts { taken: 1, not_taken: 0 }));
+    assert_eq!(coverage(JUMP_IF_FALSE, &[5]).branch(2), Some(BranchCounts { taken: 0, not_taken: 1 }));
+}
+
+#[test]
+fn merges_runs() {
+    let mut total = coverage(JUMP_IF_TRUE, &[1]);
+    total.merge(&coverage(JUMP_IF_TRUE, &[0]));
+    total.merge(&coverage(JUMP_IF_TRUE, &[0]));
+    assert_eq!(total.branch(2), Some(BranchCounts { taken: 1, not_taken: 2 }));
+    assert_eq!((total.hits(0), total.hits(5), total.hits(7)), (3, 2, 3));
+
+    let mut empty = Coverage::new();
+    empty.merge(&total);
+    assert_eq!(empty, total);
+}
+
+#[test]
+fn merges_amplifier_phase_permutations() {
+    let mut total = Coverage::new();
+    let mut best = 0;
+    for phases in permutations((0..5).collect()) {
+        let mut signal = 0;
+        for &phase in phases.iter() {
+            let outputs = RefCell::new(Vec::new());
+            let inputs = RefCell::new(vec![signal, phase]);
+            let mut program = Program::new(AMPLIFIER, || inputs.borrow_mut().pop(), |val| outputs.borrow_mut().push(val))
+                .with_coverage();
+            program.execute().result().unwrap();
+            total.merge(program.coverage().unwrap());
+            signal = outputs.borrow()[0];
+        }
+        best = best.max(signal);
+    }
+    assert_eq!(best, 43210);
+    for &pos in [0, 2, 4, 8, 12, 14].iter() {
+        assert_eq!(total.hits(pos), 120 * 5);
+    }
+    assert!(!total.executed(15));
+}
+
+#[test]
+fn annotates_disassembly() {
+    let memory = parse(JUMP_IF_TRUE);
+    let annotated = coverage(JUMP_IF_TRUE, &[1]).annotate(&memory, &Extensions::new());
+    assert_eq!(annotated.lines().collect::<Vec<_>>(), vec![
+        "       1      0: Input             [11]",
+        "       1      2: JumpIfTrue        [11], 7    ; taken 1, not taken 0",
+        "   #####      5: Output            0",
+        "       1      7: Output            1",
+        "       1      9: Halt              ",
+    ]);
+
+//    Data which isn't zero is shown after the code
+    let mut memory = memory;
+    memory[11] = 1;
+    let annotated = coverage(JUMP_IF_TRUE, &[1]).annotate(&memory, &Extensions::new());
+    assert_eq!(annotated.lines().skip(5).collect::<Vec<_>>(), vec![
+        "       -     10: data              0",
+        "       -     11: data              1",
+    ]);
+}
+
+#[test]
+fn lcov_report() {
+    let memory = parse(JUMP_IF_TRUE);
+    let mut total = coverage(JUMP_IF_TRUE, &[1]);
+    total.merge(&coverage(JUMP_IF_TRUE, &[1]));
+    assert_eq!(total.to_lcov("jump.int", &memory, &Extensions::new()), "\
+TN:
+SF:jump.int
+BRDA:2,0,0,2
+BRDA:2,0,1,0
+BRF:2
+BRH:1
+DA:0,2
+DA:2,2
+DA:5,0
+DA:7,2
+DA:9,2
+LF:5
+LH:4
+end_of_record
+");
+}
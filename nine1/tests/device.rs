@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nine1::device::{Clock, Framebuffer, Random};
+use nine1::{Device, Devices, IntcodeError, Integer, Position, Program};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Access {
+    Tick,
+    Read(Position),
+    Write(Position, Integer),
+    Input,
+}
+
+/// Memory which logs every access to it
+struct Logged {
+    values: Vec<Integer>,
+    log: Rc<RefCell<Vec<Access>>>,
+}
+
+impl Device for Logged {
+    fn read(&mut self, offset: Position) -> Integer {
+        self.log.borrow_mut().push(Access::Read(offset));
+        self.values[offset as usize]
+    }
+
+    fn write(&mut self, offset: Position, val: Integer) {
+        self.log.borrow_mut().push(Access::Write(offset, val));
+        self.values[offset as usize] = val;
+    }
+
+    fn tick(&mut self) {
+        self.log.borrow_mut().push(Access::Tick);
+    }
+}
+
+/// Program run straight out of a logged device, with the accesses it made
+fn run_logged(values: Vec<Integer>, input: Integer) -> (Result<Option<Integer>, IntcodeError>, Vec<Access>) {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut devices = Devices::new();
+    devices.attach(0..values.len() as Position, Logged { values, log: log.clone() }).unwrap();
+    let input_log = log.clone();
+    let mut program = Program::new("0", || {
+        input_log.borrow_mut().push(Access::Input);
+        Some(input)
+    }, |_| {}).with_devices(devices);
+    let result = program.execute().result();
+    let log = log.borrow().clone();
+    (result, log)
+}
+
+fn run(intcode: &str, devices: Devices) -> Vec<Integer> {
+    let outputs = RefCell::new(Vec::new());
+    let mut program = Program::new(intcode, || None, |val| outputs.borrow_mut().push(val)).with_devices(devices);
+    program.execute().result().unwrap();
+    outputs.into_inner()
+}
+
+#[test]
+fn reads_come_before_the_write() {
+    use Access::*;
+    let (result, log) = run_logged(vec![1, 5, 6, 7, 99, 2, 3, 0], 0);
+    assert_eq!(result, Ok(None));
+    assert_eq!(log, vec![
+        Tick, Read(0), Read(1), Read(5), Read(2), Read(6), Read(3), Write(7, 5),
+        Tick, Read(4),
+    ]);
+}
+
+#[test]
+fn input_comes_between_params_and_write() {
+    use Access::*;
+    let (result, log) = run_logged(vec![3, 5, 4, 5, 99, 0], 42);
+    assert_eq!(result, Ok(Some(42)));
+    assert_eq!(log, vec![
+        Tick, Read(0), Read(1), Input, Write(5, 42),
+        Tick, Read(2), Read(3), Read(5),
+        Tick, Read(4),
+    ]);
+}
+
+#[test]
+fn clock_counts_cycles() {
+    let mut devices = Devices::new();
+    devices.attach(100..101, Clock::new()).unwrap();
+    assert_eq!(run("4,100,4,100,99", devices), vec![1, 2]);
+
+    let mut devices = Devices::new();
+    devices.attach(100..101, Clock::new()).unwrap();
+    assert_eq!(run("1101,10,0,100,4,100,99", devices), vec![11]);
+}
+
+#[test]
+fn random_is_repeatable() {
+    let numbers = |seed| {
+        let mut random = Random::new(seed);
+        (0..5).map(|_| random.read(0)).collect::<Vec<_>>()
+    };
+    assert_eq!(numbers(7), numbers(7));
+    assert_ne!(numbers(7), numbers(8));
+    assert!(numbers(7).iter().chain(numbers(0).iter()).all(|&val| val >= 0));
+    assert_ne!(numbers(0)[0], numbers(0)[1]);
+
+    let mut random = Random::new(3);
+    random.read(0);
+    random.write(0, 7);
+    assert_eq!(random.read(0), numbers(7)[0]);
+
+    let mut devices = Devices::new();
+    devices.attach(100..101, Random::new(7)).unwrap();
+    assert_eq!(run("4,100,4,100,104,7,4,100,99", devices), vec![numbers(7)[0], numbers(7)[1], 7, numbers(7)[2]]);
+}
+
+#[test]
+fn framebuffer_pixels() {
+    let framebuffer = Framebuffer::new(3, 2);
+    assert_eq!(framebuffer.size(), 6);
+    let mut devices = Devices::new();
+    devices.attach(100..106, framebuffer.clone()).unwrap();
+    assert_eq!(run("1101,1,0,100,1101,5,0,104,1101,1,0,105,4,104,99", devices), vec![5]);
+    assert_eq!((framebuffer.pixel(0, 0), framebuffer.pixel(1, 1), framebuffer.pixel(2, 1)), (1, 5, 1));
+    assert_eq!(framebuffer.render(), "█  \n ██");
+}
+
+#[test]
+fn ranges_must_fit_the_device() {
+    let mut devices = Devices::new();
+    assert_eq!(devices.attach(100..200, Framebuffer::new(2, 2)), Err(IntcodeError::InvalidDeviceRange { start: 100, end: 200 }));
+    assert_eq!(devices.attach(100..100, Clock::new()), Err(IntcodeError::InvalidDeviceRange { start: 100, end: 100 }));
+    assert_eq!(devices.attach(9_999_999..10_000_001, Clock::new()),
+        Err(IntcodeError::InvalidDeviceRange { start: 9_999_999, end: 10_000_001 }));
+    assert!(devices.is_empty());
+    assert_eq!(devices.attach(100..102, Framebuffer::new(2, 2)), Ok(()));
+    assert_eq!(devices.attach(101..103, Clock::new()), Err(IntcodeError::InvalidDeviceRange { start: 101, end: 103 }));
+    assert_eq!(devices.attach(102..200, Clock::new()), Ok(()));
+}
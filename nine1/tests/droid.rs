@@ -0,0 +1,141 @@
+use nine1::compiler::compile;
+use nine1::droid::{self, Cell, DroidError, Move};
+use nine1::loader::LoadError;
+use nine1::{IntcodeError, Machine};
+
+/// Maze with two routes from the start, only one of which reaches the target, drawn with the
+/// droid starting at 1,1 of:
+///
+/// ```text
+/// #########
+/// #...#...#
+/// #.#.#.#.#
+/// #.#...#O#
+/// #.#####.#
+/// #...#...#
+/// #########
+/// ```
+const MAZE: &str = "
+    fn open(x, y) {
+        if y == 1 { return x == 1 || x == 2 || x == 3 || x == 5 || x == 6 || x == 7; }
+        if y == 2 { return x == 1 || x == 3 || x == 5 || x == 7; }
+        if y == 3 { return x == 1 || x == 3 || x == 4 || x == 5 || x == 7; }
+        if y == 4 { return x == 1 || x == 7; }
+        if y == 5 { return x == 1 || x == 2 || x == 3 || x == 5 || x == 6 || x == 7; }
+        return 0;
+    }
+
+    let x = 1;
+    let y = 1;
+    while 1 {
+        let command = input();
+        let next_x = x;
+        let next_y = y;
+        if command == 1 {
+            next_y = y - 1;
+        } else if command == 2 {
+            next_y = y + 1;
+        } else if command == 3 {
+            next_x = x - 1;
+        } else if command == 4 {
+            next_x = x + 1;
+        }
+        if open(next_x, next_y) {
+            x = next_x;
+            y = next_y;
+            output(1 + (x == 7 && y == 3));
+        } else {
+            output(0);
+        }
+    }
+";
+
+fn maze() -> String {
+    compile(MAZE).unwrap_or_else(|err| panic!("Failed to compile: {}", err))
+}
+
+#[test]
+fn shortest_path_to_the_target() {
+    let maze = droid::explore(&maze()).unwrap();
+    assert_eq!(maze.target(), Some((6, 2)));
+    use Move::*;
+    assert_eq!(maze.path(), Some(vec![East, East, South, South, East, East, North, North, East, East, South, South]));
+    assert_eq!(maze.path_to((2, 4)), Some(vec![South, South, South, South, East, East]));
+    assert_eq!(maze.path_to((3, 0)), None);
+}
+
+#[test]
+fn discovered_map() {
+    let maze = droid::explore(&maze()).unwrap();
+    assert_eq!(maze.render(), [
+        " ███ ███ ",
+        "█D..█...█",
+        "█.█.█.█.█",
+        "█.█...█O█",
+        "█.█████.█",
+        "█...█...█",
+        " ███ ███ ",
+    ].join("\n"));
+    assert_eq!(maze.cells().values().filter(|&&cell| cell != Cell::Wall).count(), 23);
+    assert_eq!(maze.cell((3, 3)), Some(Cell::Wall));
+    assert_eq!(maze.cell((3, -1)), None);
+}
+
+#[test]
+fn flood_fill_times() {
+    let maze = droid::explore(&maze()).unwrap();
+    assert_eq!(maze.fill_time(), Some(18));
+    let times = maze.fill_times(maze.target().unwrap());
+    assert_eq!(times.len(), 23);
+    assert_eq!((times[&(0, 0)], times[&(2, 4)], times[&(6, 2)]), (12, 18, 0));
+    assert_eq!(maze.fill_times((0, 0))[&(6, 2)], 12);
+    assert!(maze.fill_times((3, 0)).is_empty());
+}
+
+#[test]
+fn maze_without_a_target() {
+    let intcode = compile("
+        let x = 0;
+        while 1 {
+            let command = input();
+            if command == 3 && x > -3 {
+                x = x - 1;
+                output(1);
+            } else if command == 4 && x < 0 {
+                x = x + 1;
+                output(1);
+            } else {
+                output(0);
+            }
+        }
+    ").unwrap();
+    let maze = droid::explore(&intcode).unwrap();
+    assert_eq!(maze.target(), None);
+    assert_eq!((maze.path(), maze.fill_time()), (None, None));
+    assert_eq!(maze.render(), " ████ \n█...D█\n ████ ");
+}
+
+#[test]
+fn program_errors() {
+    let err = droid::explore("99").unwrap_err();
+    assert!(matches!(err, DroidError::NoStatus { from: (0, 0), step: Move::North }), "{:?}", err);
+    let err = droid::explore("3,100,104,5,1105,1,0").unwrap_err();
+    assert!(matches!(err, DroidError::InvalidStatus { from: (0, 0), step: Move::North, status: 5 }), "{:?}", err);
+    let err = droid::explore("3,100,1105,1,0").unwrap_err();
+    assert!(matches!(err, DroidError::NoStatus { from: (0, 0), step: Move::North }), "{:?}", err);
+    assert_eq!(err.to_string(), "No status for moving North from (0, 0)");
+
+    let machine = Machine::new(vec![3, 100, 1105, 1, 0]).with_cycle_limit(10);
+    let err = droid::explore_from(machine).unwrap_err();
+    assert!(matches!(err, DroidError::NoStatus { from: (0, 0), step: Move::North }), "{:?}", err);
+    let machine = Machine::new(vec![1105, 1, 0]).with_cycle_limit(10);
+    let err = droid::explore_from(machine).unwrap_err();
+    assert!(matches!(err, DroidError::Intcode(IntcodeError::CycleLimit { pos: 0, cycles: 10 })), "{:?}", err);
+}
+
+#[test]
+fn invalid_intcode() {
+    let err = droid::explore("3,x,99").unwrap_err();
+    assert!(matches!(err, DroidError::Load(LoadError::InvalidValue { index: 1, .. })), "{:?}", err);
+    assert_eq!(err.to_string(), "Invalid value \"x\" at index 1 on line 1");
+}
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nine1::{disassemble, read_opcode, Extension, ExtensionAction, Extensions, Instruction, IntcodeError, Opcode, ParamMode, Program};
+
+fn double() -> Extension {
+    Extension::new("double", 2, |params| ExtensionAction::Store(params[0] * 2)).with_output()
+}
+
+#[test]
+fn registration_errors() {
+    let mut extensions = Extensions::new();
+    assert_eq!(extensions.register(100, Extension::debug_print()), Err(IntcodeError::OpcodeOutOfRange(100)));
+    assert_eq!(extensions.register(50, Extension::new("wide", 4, |_| ExtensionAction::Continue)),
+        Err(IntcodeError::TooManyParams { opcode: 50, num_params: 4 }));
+    assert_eq!(extensions.register(1, Extension::debug_print()), Err(IntcodeError::OpcodeInUse(1)));
+    assert_eq!(extensions.register(99, Extension::debug_print()), Err(IntcodeError::OpcodeInUse(99)));
+    assert_eq!(extensions.register(50, Extension::new("store", 0, |_| ExtensionAction::Store(1)).with_output()),
+        Err(IntcodeError::NoOutputParam(50)));
+    assert!(extensions.get(50).is_none());
+
+    assert_eq!(extensions.register(50, Extension::debug_print()), Ok(()));
+    assert_eq!(extensions.register(50, Extension::halt_with_code()), Err(IntcodeError::OpcodeInUse(50)));
+    assert_eq!(extensions.get(50).unwrap().name(), "debug");
+    assert_eq!(extensions.register(51, Extension::new("nop", 0, |_| ExtensionAction::Continue)), Ok(()));
+    assert_eq!(extensions.register(52, Extension::new("wide", 3, |_| ExtensionAction::Continue)), Ok(()));
+}
+
+#[test]
+fn no_output_param_message() {
+    assert_eq!(IntcodeError::NoOutputParam(50).to_string(), "Extension 50 has an output but no params to write it to");
+    assert_eq!(IntcodeError::NoOutputParam(50).pos(), None);
+}
+
+#[test]
+fn decodes_registered_opcodes() {
+    let mut extensions = Extensions::new();
+    extensions.register(50, double()).unwrap();
+    assert_eq!(read_opcode(0, 250, &extensions),
+        Ok((Opcode::Extension(50), [ParamMode::RELATIVE, ParamMode::POSITION, ParamMode::POSITION])));
+    assert_eq!(read_opcode(0, 1001, &extensions),
+        Ok((Opcode::Builtin(Instruction::Add), [ParamMode::POSITION, ParamMode::IMMEDIATE, ParamMode::POSITION])));
+    assert_eq!(read_opcode(3, 51, &extensions), Err(IntcodeError::UnknownOpcode { pos: 3, opcode: 51 }));
+    assert_eq!(read_opcode(3, 50, &Extensions::new()), Err(IntcodeError::UnknownOpcode { pos: 3, opcode: 50 }));
+    assert_eq!(read_opcode(3, 350, &extensions), Err(IntcodeError::InvalidParamMode { pos: 3, opcode: 350, mode: 3 }));
+}
+
+#[test]
+fn runs_extensions() {
+    let printed = Rc::new(RefCell::new(Vec::new()));
+    let mut extensions = Extensions::new();
+    extensions.register(50, double()).unwrap();
+    let log = printed.clone();
+    extensions.register(51, Extension::new("log", 1, move |params| {
+        log.borrow_mut().push(params[0]);
+        ExtensionAction::Continue
+    })).unwrap();
+    extensions.register(52, Extension::new("skip", 0, |_| ExtensionAction::Jump(12))).unwrap();
+    extensions.register(53, Extension::halt_with_code()).unwrap();
+
+    let outputs = RefCell::new(Vec::new());
+    let mut program = Program::new("150,21,20,51,20,52,104,0,99,0,0,0,4,20,1153,7,0,0,0,0,0", || None, |val| outputs.borrow_mut().push(val))
+        .with_extensions(extensions);
+    assert_eq!(program.execute().result(), Ok(Some(42)));
+    assert_eq!(program.halt_code(), Some(7));
+    assert_eq!(*outputs.borrow(), vec![42]);
+    assert_eq!(*printed.borrow(), vec![42]);
+}
+
+#[test]
+fn store_without_output_param() {
+    let mut extensions = Extensions::new();
+    extensions.register(50, Extension::new("store", 1, |_| ExtensionAction::Store(1))).unwrap();
+    let mut program = Program::new("50,0,99", || None, |_| {}).with_extensions(extensions);
+    assert_eq!(program.execute().result(), Err(IntcodeError::MissingOutputParam { pos: 0, opcode: 50 }));
+}
+
+#[test]
+fn unregistered_opcodes_are_errors() {
+    let mut program = Program::new("50,99", || None, |_| {});
+    assert_eq!(program.execute().result(), Err(IntcodeError::UnknownOpcode { pos: 0, opcode: 50 }));
+}
+
+#[test]
+fn disassembles_extensions() {
+    let mut extensions = Extensions::new();
+    extensions.register(50, double()).unwrap();
+    extensions.register(51, Extension::debug_print()).unwrap();
+    let memory = [2150, 7, 1, 151, -3, 50, 99];
+    let lines: Vec<String> = disassemble(&memory, &extensions).iter().map(|line| line.to_string()).collect();
+    assert_eq!(lines, vec![
+        "     0: double            7, [rb+1]",
+        "     3: debug             -3",
+        "     5: data              50",
+        "     6: Halt              ",
+    ]);
+    let lines: Vec<String> = disassemble(&memory[..3], &Extensions::new()).iter().map(|line| line.to_string()).collect();
+    assert_eq!(lines, vec![
+        "     0: data              2150",
+        "     1: data              7",
+        "     2: data              1",
+    ]);
+}
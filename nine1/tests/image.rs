@@ -0,0 +1,150 @@
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+use nine1::image::{is_image, Image, MAGIC};
+use nine1::loader::{self, parse, to_text, LoadError};
+use nine1::Integer;
+
+/// Intcode string literals in Rust source, such as the programs in main.rs
+fn programs_in(source: &str) -> Vec<Vec<Integer>> {
+    source.split('"')
+        .skip(1)
+        .step_by(2)
+        .filter(|literal| literal.contains(',') && literal.chars().all(|c| c == ',' || c == '-' || c.is_ascii_digit()))
+        .map(|literal| parse(literal).unwrap())
+        .collect()
+}
+
+fn invalid_image(bytes: &[u8]) -> String {
+    match Image::from_bytes(bytes) {
+        Err(LoadError::InvalidImage(reason)) => reason,
+        other => panic!("Expected an invalid image but got {:?}", other),
+    }
+}
+
+/// Replace the checksum at the end of a modified image
+fn fix_checksum(bytes: &mut Vec<u8>) {
+    bytes.truncate(bytes.len() - 4);
+    let crc = match Image::from_bytes(&[&bytes[..], &[0; 4]].concat()) {
+        Err(LoadError::ChecksumMismatch { actual, .. }) => actual,
+        other => panic!("Expected a checksum mismatch but got {:?}", other),
+    };
+    bytes.extend_from_slice(&crc.to_le_bytes());
+}
+
+#[test]
+fn round_trips_nine1_programs() {
+    let mut programs = programs_in(include_str!("../src/main.rs"));
+    programs.push(parse(include_str!("../aot-tests/programs/boost.txt")).unwrap());
+    programs.push(parse(include_str!("../aot-tests/programs/day9_large_number.txt")).unwrap());
+    assert_eq!(programs.len(), 6);
+    for memory in programs {
+        let image = Image::new(memory.clone());
+        let bytes = image.to_bytes();
+//        Apart from the header, images are always smaller than the text
+        assert!(bytes.len() < to_text(&memory).len() + 12);
+        assert_eq!(Image::from_bytes(&bytes).unwrap(), image);
+        assert_eq!(loader::read_from(Cursor::new(bytes)).unwrap(), memory);
+    }
+}
+
+#[test]
+fn images_are_smaller_than_text() {
+    let memory = parse(include_str!("../aot-tests/programs/boost.txt")).unwrap();
+    let bytes = Image::new(memory.clone()).to_bytes();
+    assert!(bytes.len() * 2 < to_text(&memory).len(), "{} bytes", bytes.len());
+}
+
+#[test]
+fn round_trips_seven2_programs() {
+    let programs = programs_in(include_str!("../../seven2/src/main.rs"));
+    assert_eq!(programs.len(), 1);
+    for memory in programs {
+        let image = Image::new(memory.clone()).with_width(32);
+        let read = Image::from_bytes(&image.to_bytes()).unwrap();
+        assert_eq!(read.width, 32);
+        assert_eq!(read.memory, memory);
+    }
+}
+
+#[test]
+fn round_trips_extreme_values_and_symbols() {
+    let image = Image::new(vec![0, -1, 1, 63, -64, 64, -65, Integer::MAX, Integer::MIN])
+        .with_symbol(0, "main")
+        .with_symbol(1_000_000, "")
+        .with_symbol(u64::MAX, "ünïcode");
+    assert_eq!(Image::from_bytes(&image.to_bytes()).unwrap(), image);
+
+    let narrow = Image::new(vec![127, -128]).with_width(8);
+    assert_eq!(Image::from_bytes(&narrow.to_bytes()).unwrap(), narrow);
+}
+
+#[test]
+fn encoding() {
+    let bytes = Image::new(vec![0, -1, 1, -64, 64]).to_bytes();
+    assert_eq!(&bytes[..4], MAGIC);
+    assert_eq!(&bytes[4..bytes.len() - 4], &[1, 64, 0, 5, 0, 1, 2, 127, 128, 1]);
+    assert!(is_image(&bytes));
+    assert!(!is_image(b"1,2,3"));
+}
+
+#[test]
+#[should_panic(expected = "Value 128 at index 1 doesn't fit in 8 bits")]
+fn writing_values_which_are_too_wide() {
+    Image::new(vec![0, 128]).with_width(8).to_bytes();
+}
+
+#[test]
+fn invalid_images() {
+    let bytes = Image::new(vec![1, 2, 3]).with_symbol(0, "main").to_bytes();
+
+    assert_eq!(invalid_image(b"1,2,3"), "Image doesn't start with the image header");
+    assert_eq!(invalid_image(&bytes[..6]), "Image ends in the middle of the header");
+
+    let mut truncated = bytes[..bytes.len() - 8].to_vec();
+    truncated.extend_from_slice(&[0; 4]);
+    fix_checksum(&mut truncated);
+    assert_eq!(invalid_image(&truncated), "The symbol name length at byte 13 is larger than the image");
+
+    let mut version = bytes.clone();
+    version[4] = 2;
+    fix_checksum(&mut version);
+    assert_eq!(invalid_image(&version), "Image version 2 isn't supported");
+
+    let mut width = bytes.clone();
+    width[5] = 2;
+    fix_checksum(&mut width);
+    assert_eq!(invalid_image(&width), "Value 2 at index 1 doesn't fit in 2 bits");
+
+    let mut length = bytes.clone();
+    length[7] = 100;
+    fix_checksum(&mut length);
+    assert_eq!(invalid_image(&length), "The length at byte 7 is larger than the image");
+
+    let mut extra = bytes.clone();
+    extra.insert(extra.len() - 4, 0);
+    fix_checksum(&mut extra);
+    assert_eq!(invalid_image(&extra), "Image has 1 unexpected bytes at the end");
+}
+
+#[test]
+fn checksum_mismatch() {
+    let mut bytes = Image::new(vec![104, 1, 99]).to_bytes();
+    bytes[8] ^= 1;
+    let err = Image::from_bytes(&bytes).unwrap_err();
+    assert!(matches!(err, LoadError::ChecksumMismatch { .. }));
+    assert!(err.to_string().starts_with("Image checksum is 0x"));
+}
+
+#[test]
+fn save_and_load() {
+    let image = Image::new(vec![3, 0, 4, 0, 99]).with_symbol(0, "echo");
+    let path = env::temp_dir().join(format!("nine1-image-{}.bin", std::process::id()));
+    image.save(&path).unwrap();
+    let loaded = Image::load(&path);
+    let memory = loader::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), image);
+    assert_eq!(memory.unwrap(), vec![3, 0, 4, 0, 99]);
+}
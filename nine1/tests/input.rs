@@ -0,0 +1,18 @@
+use std::cell::RefCell;
+
+use nine1::{IntcodeError, Program};
+
+/// Reads two values and outputs their sum
+const ADD_INPUTS: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+
+#[test]
+fn reads_until_input_runs_out() {
+    let inputs = RefCell::new(vec![3, 4].into_iter());
+    let mut program = Program::new(ADD_INPUTS, || inputs.borrow_mut().next(), |_| {});
+    assert_eq!(program.execute().result(), Ok(Some(7)));
+
+    let inputs = RefCell::new(vec![3].into_iter());
+    let mut program = Program::new(ADD_INPUTS, || inputs.borrow_mut().next(), |_| {});
+    assert_eq!(program.execute().result(), Err(IntcodeError::InputExhausted { pos: 2 }));
+    assert_eq!(IntcodeError::InputExhausted { pos: 2 }.to_string(), "Ran out of input for instruction at pos 2");
+}
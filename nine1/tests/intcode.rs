@@ -0,0 +1,154 @@
+//! Runs the intcode binary, checking how it parses its arguments, where it takes input from and
+//! what it exits with
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Adds up its inputs until it reads 0, outputting the running total after each one
+const TOTALS: &str = "3,100,1006,100,14,1,100,101,101,4,101,1105,1,0,99";
+/// Prompts with "?" and echoes each line it reads back with a "> " in front, until it reads "."
+const ECHO: &str = "104,63,3,100,1008,100,46,101,1005,101,34,104,62,104,32,1008,100,10,101,1005,101,29,\
+    4,100,3,100,1105,1,15,104,10,1105,1,0,104,98,104,121,104,101,104,10,99";
+
+/// File in the temp directory which is removed again when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("nine1-intcode-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn intcode(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_intcode"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+/// Exit code and the first line of stderr, for runs which fail before running the program
+fn usage_error(args: &[&str]) -> (Option<i32>, String) {
+    let output = intcode(args, "");
+    (output.status.code(), stderr(&output).lines().next().unwrap_or("").to_owned())
+}
+
+#[test]
+fn help() {
+    let output = intcode(&["--help"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("Usage: intcode [OPTIONS] [PROGRAM]"));
+    assert_eq!(intcode(&["-h"], "").status.code(), Some(0));
+}
+
+#[test]
+fn argument_errors() {
+    assert_eq!(usage_error(&["-x"]), (Some(2), "Unknown option: -x".to_owned()));
+    assert_eq!(usage_error(&["a.txt", "b.txt"]), (Some(2), "Unexpected argument: b.txt".to_owned()));
+    assert_eq!(usage_error(&["-i"]), (Some(2), "Missing values for --input".to_owned()));
+    assert_eq!(usage_error(&["-i", "1,x"]), (Some(2), "Invalid input value: x".to_owned()));
+    for option in ["--input-file", "--snapshot", "--record", "--replay"].iter() {
+        assert_eq!(usage_error(&[option]), (Some(2), format!("Missing file for {}", option)));
+    }
+    assert_eq!(usage_error(&["-I"]), (Some(2), "Can't read both the program and interactive input from stdin".to_owned()));
+    assert_eq!(usage_error(&["-", "--interactive"]),
+        (Some(2), "Can't read both the program and interactive input from stdin".to_owned()));
+    assert_eq!(usage_error(&["a.txt", "-R", "s.txt", "-i", "1"]),
+        (Some(2), "--replay takes its inputs from the session file".to_owned()));
+    assert_eq!(usage_error(&["a.txt", "-R", "s.txt", "-r", "t.txt"]),
+        (Some(2), "--replay takes its inputs from the session file".to_owned()));
+}
+
+#[test]
+fn missing_program() {
+    let (code, message) = usage_error(&["/nonexistent/program.txt"]);
+    assert_eq!(code, Some(2));
+    assert!(message.starts_with("Couldn't read program: "), "{}", message);
+}
+
+#[test]
+fn program_from_stdin() {
+    let output = intcode(&["-i", "2,3", "-i", "0"], TOTALS);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2\n5\n");
+    assert_eq!(stdout(&intcode(&["-", "-i", "4 0"], TOTALS)), "4\n");
+}
+
+#[test]
+fn input_sources() {
+    let program = TempFile::new("totals.txt", TOTALS);
+    let inputs = TempFile::new("inputs.txt", "3\n4, 5\n");
+    let output = intcode(&[program.path(), "-i", "1", "-f", inputs.path(), "-I"], "x\n\n6 0\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1\n4\n8\n13\n19\n");
+    assert_eq!(stderr(&output), "Invalid input value: x\n");
+
+//    Stdin is only read with --interactive
+    let output = intcode(&[program.path(), "-i", "1"], "0\n");
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn ascii_input_sources() {
+    let program = TempFile::new("echo.txt", ECHO);
+    let commands = TempFile::new("commands.txt", "hi\n");
+    let output = intcode(&[program.path(), "-a", "-f", commands.path(), "-I"], "there\n.\n");
+    assert_eq!(stderr(&output), "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "?\n> hi\n?\n> there\n?\nbye\n");
+}
+
+#[test]
+fn vm_errors_exit_with_1() {
+    let output = intcode(&["-i", "2"], TOTALS);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "2\n");
+    assert_eq!(stderr(&output), "Error: Ran out of input for instruction at pos 0\n");
+
+    let output = intcode(&[], "104,1,50");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Error: Unknown opcode 50 at pos 2\n");
+
+    let output = intcode(&[], "4,10000000,99");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Error: Address 10000000 used at pos 1 is past the end of memory\n");
+
+    let output = intcode(&[], "1101,9223372036854775807,1,5,99,0");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Error: Arithmetic overflowed at pos 0\n");
+}
+
+#[test]
+fn lint_problems_exit_with_1() {
+    let output = intcode(&["--lint"], "1105,1,100,99");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "0: Jump to 100 is outside of the program\n");
+    assert_eq!(stdout(&output), "");
+    assert_eq!(intcode(&["--lint"], "104,1,99").status.code(), Some(0));
+}
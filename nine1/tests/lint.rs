@@ -0,0 +1,66 @@
+use nine1::compiler::compile;
+use nine1::lint::{lint, Diagnostic, Problem};
+use nine1::{loader, Extension, ExtensionAction, Extensions, Integer};
+
+fn problems(memory: &[Integer]) -> Vec<(u64, Problem)> {
+    lint(memory, &Extensions::new()).into_iter().map(|Diagnostic { pos, problem }| (pos, problem)).collect()
+}
+
+#[test]
+fn valid_programs() {
+    for intcode in [
+        include_str!("../aot-tests/programs/boost.txt"),
+        include_str!("../aot-tests/programs/day5_compare_to_8.txt"),
+        include_str!("../aot-tests/programs/day9_quine.txt"),
+    ].iter() {
+        assert_eq!(problems(&loader::parse(intcode).unwrap()), vec![], "{}", intcode);
+    }
+    let fib = compile(include_str!("../aot-tests/programs/fib.src")).unwrap();
+    assert_eq!(problems(&loader::parse(&fib).unwrap()), vec![]);
+}
+
+#[test]
+fn unknown_opcodes_and_modes() {
+    assert_eq!(problems(&[1101, 1, 1, 20, 42]), vec![(4, Problem::UnknownOpcode { opcode: 42 })]);
+    assert_eq!(problems(&[-1]), vec![(0, Problem::UnknownOpcode { opcode: -1 })]);
+    assert_eq!(problems(&[304, 5, 99]), vec![(0, Problem::InvalidParamMode { opcode: 304, mode: 3 })]);
+    assert_eq!(problems(&[90004, 5, 99]), vec![(0, Problem::InvalidParamMode { opcode: 90004, mode: 9 })]);
+}
+
+#[test]
+fn immediate_writes() {
+    assert_eq!(problems(&[11101, 1, 1, 3, 103, 7, 99]), vec![
+        (0, Problem::ImmediateWrite { param: 3 }),
+        (4, Problem::ImmediateWrite { param: 1 }),
+    ]);
+    let mut extensions = Extensions::new();
+    extensions.register(50, Extension::new("store", 2, |params| ExtensionAction::Store(params[0])).with_output()).unwrap();
+    let diagnostics = lint(&[11150, 1, 2, 99], &extensions);
+    assert_eq!(diagnostics, vec![Diagnostic { pos: 0, problem: Problem::ImmediateWrite { param: 2 } }]);
+    assert_eq!(diagnostics[0].to_string(), "0: Output param 2 uses IMMEDIATE param mode");
+}
+
+#[test]
+fn jumps() {
+    assert_eq!(problems(&[1105, 1, -1]), vec![(0, Problem::JumpOutOfRange { target: -1 })]);
+    assert_eq!(problems(&[1006, 10, 50, 99]), vec![(0, Problem::JumpOutOfRange { target: 50 })]);
+//    Jumps which are never taken go nowhere, and ones which always are never fall through
+    assert_eq!(problems(&[1105, 0, 50, 1106, 0, 7, 42, 99]), vec![]);
+//    Code reached by a jump is checked
+    assert_eq!(problems(&[1105, 1, 4, 42, 4, 3, 42]), vec![(6, Problem::UnknownOpcode { opcode: 42 })]);
+//    Targets read from memory aren't known, so they aren't followed
+    assert_eq!(problems(&[5, 10, 11, 99, 42]), vec![]);
+}
+
+#[test]
+fn truncated_instructions() {
+    let diagnostics = lint(&[104, 1, 1101, 2], &Extensions::new());
+    assert_eq!(diagnostics, vec![Diagnostic { pos: 2, problem: Problem::Truncated { size: 4, available: 2 } }]);
+    assert_eq!(diagnostics[0].to_string(), "2: Instruction takes 4 values but the program ends after 2");
+}
+
+#[test]
+fn unreachable_code_is_ignored() {
+    assert_eq!(problems(&[99, 42, 11101, 1]), vec![]);
+    assert_eq!(problems(&[]), vec![]);
+}
@@ -0,0 +1,116 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+use nine1::image::Image;
+use nine1::loader::{load, parse, read_from, to_text, LoadError};
+use nine1::{Integer, Program};
+
+fn invalid(text: &str) -> (usize, usize, String) {
+    match parse(text) {
+        Err(LoadError::InvalidValue { index, line, text }) => (index, line, text),
+        other => panic!("Expected an invalid value but got {:?}", other),
+    }
+}
+
+#[test]
+fn plain_text() {
+    assert_eq!(parse("1,9,10,3,2,3,11,0,99,30,40,50").unwrap(), vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+    assert_eq!(parse("-1").unwrap(), vec![-1]);
+    assert_eq!(parse("1125899906842624").unwrap(), vec![1125899906842624]);
+}
+
+#[test]
+fn whitespace_and_trailing_comma() {
+    assert_eq!(parse("3,0,4,0,99\n").unwrap(), vec![3, 0, 4, 0, 99]);
+    assert_eq!(parse("3,0,4,0,99\r\n").unwrap(), vec![3, 0, 4, 0, 99]);
+    assert_eq!(parse("  3, 0,\t4 ,0 , 99  ").unwrap(), vec![3, 0, 4, 0, 99]);
+    assert_eq!(parse("3,0,\n4,0,\n99,\n").unwrap(), vec![3, 0, 4, 0, 99]);
+}
+
+#[test]
+fn comments() {
+    let text = "# Outputs its input\n3,0, # input\n4,0,  # output\n# and stop\n99\n# the end";
+    assert_eq!(parse(text).unwrap(), vec![3, 0, 4, 0, 99]);
+    assert_eq!(parse("104,1,#,2\n99").unwrap(), vec![104, 1, 99]);
+}
+
+#[test]
+fn invalid_values() {
+    assert_eq!(invalid("1,2,x,4"), (2, 1, "x".to_owned()));
+    assert_eq!(invalid("1,2,\n\n3.5,4"), (2, 3, "3.5".to_owned()));
+    assert_eq!(invalid("1 2,3"), (0, 1, "1 2".to_owned()));
+    assert_eq!(invalid("1,2\n3"), (1, 1, "2\n3".to_owned()));
+    assert_eq!(invalid("99999999999999999999"), (0, 1, "99999999999999999999".to_owned()));
+}
+
+#[test]
+fn missing_values() {
+    assert_eq!(invalid("1,,2"), (1, 1, "".to_owned()));
+    assert_eq!(invalid(",1"), (0, 1, "".to_owned()));
+    assert_eq!(invalid("1,\n,2"), (1, 2, "".to_owned()));
+    assert_eq!(invalid("1,2,,"), (2, 1, "".to_owned()));
+}
+
+#[test]
+fn empty_programs() {
+    assert!(matches!(parse(""), Err(LoadError::Empty)));
+    assert!(matches!(parse(" \n\n"), Err(LoadError::Empty)));
+    assert!(matches!(parse("# nothing here\n"), Err(LoadError::Empty)));
+}
+
+#[test]
+fn programs_too_large_for_memory() {
+    let text = "0,".repeat(10_000_000) + "1";
+    assert!(matches!(parse(&text), Err(LoadError::TooLarge { len: 10_000_001 })));
+    assert!(matches!(Program::read_from(Cursor::new(text), || None, |_| {}), Err(LoadError::TooLarge { .. })));
+    let image = Image::new(vec![0; 10_000_001]).to_bytes();
+    assert!(matches!(read_from(Cursor::new(image)), Err(LoadError::TooLarge { len: 10_000_001 })));
+    assert_eq!(LoadError::TooLarge { len: 10_000_001 }.to_string(), "Program has 10000001 values but memory only holds 10000000");
+}
+
+#[test]
+fn error_messages() {
+    assert_eq!(parse("1,\n2,x").unwrap_err().to_string(), "Invalid value \"x\" at index 2 on line 2");
+    assert_eq!(parse("1,,2").unwrap_err().to_string(), "Missing value at index 1 on line 1");
+    assert_eq!(parse("").unwrap_err().to_string(), "Program is empty");
+}
+
+#[test]
+fn reading_and_loading() {
+    assert_eq!(read_from(Cursor::new("3,0,4,0,99\n")).unwrap(), vec![3, 0, 4, 0, 99]);
+    assert!(matches!(read_from(Cursor::new(vec![0xff, 0xfe])), Err(LoadError::Io(_))));
+
+    let path = env::temp_dir().join(format!("nine1-loader-{}.txt", std::process::id()));
+    fs::write(&path, "# Outputs its input\n3,0,4,0,99\n").unwrap();
+    let loaded = load(&path);
+    let outputs = RefCell::new(Vec::new());
+    let program = Program::load(&path, || Some(42), |val| outputs.borrow_mut().push(val));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.unwrap(), vec![3, 0, 4, 0, 99]);
+    assert_eq!(program.unwrap().execute().result(), Ok(Some(42)));
+    assert_eq!(outputs.into_inner(), vec![42]);
+    assert!(matches!(load(&path), Err(LoadError::Io(_))));
+}
+
+#[test]
+fn program_from_reader() {
+    let mut program = Program::read_from(Cursor::new("104,7,\n99\n"), || None, |_| {}).unwrap();
+    assert_eq!(program.execute().result(), Ok(Some(7)));
+    assert!(Program::read_from(Cursor::new("104,x,99"), || None, |_| {}).is_err());
+}
+
+#[test]
+fn new_accepts_file_contents() {
+    let mut program = Program::new("104,7,99\n", || None, |_| {});
+    assert_eq!(program.execute().result(), Ok(Some(7)));
+}
+
+#[test]
+fn to_text_round_trips() {
+    let memory: Vec<Integer> = vec![1102, -34463338, 0, Integer::MIN, Integer::MAX, 99];
+    assert_eq!(to_text(&memory), format!("1102,-34463338,0,{},{},99", Integer::MIN, Integer::MAX));
+    assert_eq!(parse(&to_text(&memory)).unwrap(), memory);
+}
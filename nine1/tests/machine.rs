@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+
+use nine1::{IntcodeError, Machine, Program, State};
+
+/// Adds up its inputs until it reads 0, outputting the running total after each one
+const TOTALS: &str = "3,100,1006,100,14,1,100,101,101,4,101,1105,1,0,99";
+
+fn totals() -> Machine {
+    Machine::new(nine1::loader::parse(TOTALS).unwrap())
+}
+
+#[test]
+fn forks_run_independently() {
+    let mut machine = totals();
+    machine.push_input(5);
+    assert_eq!(machine.resume(), Ok(State::Output(5)));
+
+    let mut fork = machine.clone();
+    machine.push_input(1);
+    fork.push_input(10);
+    assert_eq!(machine.resume(), Ok(State::Output(6)));
+    assert_eq!(fork.resume(), Ok(State::Output(15)));
+    assert_eq!((machine.memory()[101], fork.memory()[101]), (6, 15));
+}
+
+#[test]
+fn waits_for_input() {
+    let mut machine = totals().with_recording();
+    assert_eq!(machine.resume(), Ok(State::NeedsInput));
+    assert_eq!(machine.resume(), Ok(State::NeedsInput));
+    assert_eq!((machine.pc(), machine.cycles()), (0, 1));
+
+    machine.push_input(3);
+    machine.push_input(0);
+    assert_eq!(machine.resume(), Ok(State::Output(3)));
+    assert_eq!(machine.resume(), Ok(State::Halted));
+    assert!(machine.is_halted());
+    assert_eq!(machine.resume(), Ok(State::Halted));
+    assert_eq!(machine.cycles(), 8);
+    assert_eq!(machine.session().unwrap().inputs().collect::<Vec<_>>(), vec![3, 0]);
+}
+
+#[test]
+fn same_as_program() {
+    let inputs = RefCell::new(vec![0, 4, 3]);
+    let outputs = RefCell::new(Vec::new());
+    let mut program = Program::new(TOTALS, || inputs.borrow_mut().pop(), |val| outputs.borrow_mut().push(val));
+    assert_eq!(program.execute().result(), Ok(Some(7)));
+
+    let mut machine = totals();
+    for &input in [3, 4, 0].iter() {
+        machine.push_input(input);
+    }
+    let mut machine_outputs = Vec::new();
+    while let State::Output(val) = machine.resume().unwrap() {
+        machine_outputs.push(val);
+    }
+    assert_eq!(*outputs.borrow(), machine_outputs);
+    assert_eq!(program.cycles(), machine.cycles());
+    assert!(program.machine().memory_diff(&machine).is_empty());
+}
+
+#[test]
+fn program_from_machine() {
+    let mut machine = totals();
+    machine.push_input(2);
+    assert_eq!(machine.resume(), Ok(State::Output(2)));
+
+    let outputs = RefCell::new(Vec::new());
+    let inputs = RefCell::new(vec![0, 8]);
+    let mut program = Program::from_machine(machine.clone(), || inputs.borrow_mut().pop(), |val| outputs.borrow_mut().push(val));
+    let mut stepped = program.machine().clone();
+    stepped.push_input(8);
+    assert_eq!(stepped.resume(), Ok(State::Output(10)));
+
+    assert_eq!(program.resume().result(), Ok(Some(10)));
+    assert_eq!(*outputs.borrow(), vec![10]);
+    assert_eq!(Program::from_machine(totals(), || None, |_| {}).execute().result(), Err(IntcodeError::InputExhausted { pos: 0 }));
+}
+
+#[test]
+fn resume_carries_on_where_execute_starts_again() {
+    let mut machine = Machine::new(vec![104, 1, 3, 9, 4, 9, 99, 0, 0, 0]);
+    assert_eq!(machine.resume(), Ok(State::Output(1)));
+    machine.push_input(5);
+
+    let outputs = RefCell::new(Vec::new());
+    let inputs = RefCell::new(vec![7]);
+    let mut program = Program::from_machine(machine.clone(), || inputs.borrow_mut().pop(), |val| outputs.borrow_mut().push(val));
+    assert_eq!(program.resume().result(), Ok(Some(5)));
+    assert_eq!(*outputs.borrow(), vec![5]);
+    assert_eq!(*inputs.borrow(), vec![7]);
+    assert_eq!(program.resume().result(), Ok(Some(5)));
+
+    outputs.borrow_mut().clear();
+    let mut program = Program::from_machine(machine, || inputs.borrow_mut().pop(), |val| outputs.borrow_mut().push(val));
+    assert_eq!(program.execute().result(), Ok(Some(7)));
+    assert_eq!(*outputs.borrow(), vec![1, 7]);
+}
+
+fn error(values: Vec<i64>) -> Result<State, IntcodeError> {
+    Machine::new(values).resume()
+}
+
+#[test]
+fn addresses_past_the_end_of_memory() {
+    assert_eq!(error(vec![4, 10_000_000, 99]), Err(IntcodeError::AddressOutOfRange { pos: 1, address: 10_000_000 }));
+    assert_eq!(error(vec![1101, 1, 1, 10_000_000, 99]), Err(IntcodeError::AddressOutOfRange { pos: 3, address: 10_000_000 }));
+    assert_eq!(error(vec![109, 9_999_999, 204, 1, 99]), Err(IntcodeError::AddressOutOfRange { pos: 3, address: 10_000_000 }));
+//    Output instruction in the last cell, with its param past the end
+    assert_eq!(error(vec![1101, 104, 0, 9_999_999, 1105, 1, 9_999_999]),
+        Err(IntcodeError::AddressOutOfRange { pos: 10_000_000, address: 10_000_000 }));
+}
+
+#[test]
+fn overflow() {
+    assert_eq!(error(vec![1101, i64::MAX, 1, 5, 99, 0]), Err(IntcodeError::Overflow { pos: 0 }));
+    assert_eq!(error(vec![1102, i64::MIN, 2, 5, 99, 0]), Err(IntcodeError::Overflow { pos: 0 }));
+    assert_eq!(error(vec![109, i64::MAX, 109, 1, 99]), Err(IntcodeError::Overflow { pos: 2 }));
+    assert_eq!(error(vec![109, i64::MAX, 204, 1, 99]), Err(IntcodeError::Overflow { pos: 3 }));
+    assert_eq!(error(vec![1101, i64::MAX, 0, 5, 104, 0, 99]), Ok(State::Output(i64::MAX)));
+}
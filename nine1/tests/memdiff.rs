@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use nine1::{code_addresses, diff_memory, ChangedRange, Coverage, Extensions, IntcodeError, Integer, Position, Program, Region};
+
+/// Amplifier controller software from day 7
+const AMPLIFIER: &str = "3,8,1001,8,10,8,105,1,0,0,21,34,51,76,101,114,195,276,357,438,99999,3,9,1001,9,3,9,1002,9,3,9,4,9,99,3,9,101,4,9,9,102,4,9,9,1001,9,3,9,4,9,99,3,9,1002,9,4,9,101,4,9,9,1002,9,5,9,101,4,9,9,102,3,9,9,4,9,99,3,9,101,3,9,9,1002,9,3,9,101,3,9,9,1002,9,5,9,101,4,9,9,4,9,99,3,9,1002,9,4,9,1001,9,2,9,4,9,99,3,9,1002,9,2,9,4,9,3,9,101,2,9,9,4,9,3,9,1001,9,2,9,4,9,3,9,1002,9,2,9,4,9,3,9,1002,9,2,9,4,9,3,9,1001,9,1,9,4,9,3,9,1001,9,1,9,4,9,3,9,101,2,9,9,4,9,3,9,101,2,9,9,4,9,3,9,101,1,9,9,4,9,99,3,9,101,2,9,9,4,9,3,9,102,2,9,9,4,9,3,9,1002,9,2,9,4,9,3,9,101,2,9,9,4,9,3,9,1001,9,1,9,4,9,3,9,101,2,9,9,4,9,3,9,1002,9,2,9,4,9,3,9,101,2,9,9,4,9,3,9,1001,9,2,9,4,9,3,9,101,1,9,9,4,9,99,3,9,1002,9,2,9,4,9,3,9,101,1,9,9,4,9,3,9,1002,9,2,9,4,9,3,9,1001,9,1,9,4,9,3,9,1001,9,1,9,4,9,3,9,101,1,9,9,4,9,3,9,102,2,9,9,4,9,3,9,1002,9,2,9,4,9,3,9,1001,9,1,9,4,9,3,9,102,2,9,9,4,9,99,3,9,1001,9,2,9,4,9,3,9,102,2,9,9,4,9,3,9,101,1,9,9,4,9,3,9,101,2,9,9,4,9,3,9,101,1,9,9,4,9,3,9,102,2,9,9,4,9,3,9,1002,9,2,9,4,9,3,9,101,2,9,9,4,9,3,9,102,2,9,9,4,9,3,9,101,1,9,9,4,9,99,3,9,101,2,9,9,4,9,3,9,1001,9,2,9,4,9,3,9,102,2,9,9,4,9,3,9,1001,9,1,9,4,9,3,9,101,1,9,9,4,9,3,9,102,2,9,9,4,9,3,9,101,2,9,9,4,9,3,9,1001,9,2,9,4,9,3,9,1001,9,2,9,4,9,3,9,101,2,9,9,4,9,99";
+
+fn range(start: Position, old: &[Integer], new: &[Integer], region: Region) -> ChangedRange {
+    ChangedRange { start, old: old.to_vec(), new: new.to_vec(), region }
+}
+
+fn amplifier(phase: Integer) -> Program<impl Fn() -> Option<Integer>, impl Fn(Integer)> {
+    let inputs = std::cell::RefCell::new(vec![0, phase]);
+    Program::new(AMPLIFIER, move || inputs.borrow_mut().pop(), |_| {}).with_coverage()
+}
+
+#[test]
+fn amplifiers_with_different_phases() {
+    let mut first = amplifier(0);
+    let mut second = amplifier(3);
+    first.execute().result().unwrap();
+    second.execute().result().unwrap();
+
+    let diff = first.memory_diff(&second);
+//    The phase picks the jump target at 8, then each phase's code works on the signal at 9
+    assert_eq!(diff.ranges, vec![
+        range(8, &[10], &[13], Region::Code),
+        range(9, &[9], &[64], Region::Data),
+    ]);
+    assert_eq!(diff.changed(), 2);
+    assert!(first.memory_diff(&first).is_empty());
+}
+
+#[test]
+fn self_modifying_code() {
+    let intcode = "1,9,10,3,2,3,11,0,99,30,40,50";
+    let initial = Program::new(intcode, || None, |_| {});
+    let mut program = Program::new(intcode, || None, |_| {});
+    program.execute().result().unwrap();
+    assert_eq!(initial.memory_diff(&program).ranges, vec![
+        range(0, &[1], &[3500], Region::Code),
+        range(3, &[3], &[70], Region::Code),
+    ]);
+}
+
+#[test]
+fn intermediate_state() {
+    let intcode = "1101,1,2,20,1101,3,4,21,1101,5,6,22,99";
+    let mut partial = Program::new(intcode, || None, |_| {}).with_cycle_limit(1);
+    let mut complete = Program::new(intcode, || None, |_| {});
+    assert_eq!(partial.execute().result(), Err(IntcodeError::CycleLimit { pos: 4, cycles: 1 }));
+    complete.execute().result().unwrap();
+    assert_eq!(partial.memory_diff(&complete).ranges, vec![range(21, &[0, 0], &[7, 11], Region::Data)]);
+}
+
+#[test]
+fn ranges_split_between_code_and_data() {
+    let code: BTreeSet<Position> = (2..4).collect();
+    let diff = diff_memory(&[0, 0, 0, 0, 0, 0], &[1, 1, 1, 1, 0, 1], &code);
+    assert_eq!(diff.ranges, vec![
+        range(0, &[0, 0], &[1, 1], Region::Data),
+        range(2, &[0, 0], &[1, 1], Region::Code),
+        range(5, &[0], &[1], Region::Data),
+    ]);
+}
+
+#[test]
+fn memories_of_different_lengths() {
+    let diff = diff_memory(&[1, 2], &[1, 2, 0, 4], &BTreeSet::new());
+    assert_eq!(diff.ranges, vec![range(3, &[0], &[4], Region::Data)]);
+    let diff = diff_memory(&[1, 2, 3], &[1], &BTreeSet::new());
+    assert_eq!(diff.ranges, vec![range(1, &[2, 3], &[0, 0], Region::Data)]);
+}
+
+#[test]
+fn code_from_coverage_or_disassembly() {
+    let memory = [1105, 1, 7, 1101, 1, 1, 0, 99];
+    let extensions = Extensions::new();
+    assert_eq!(code_addresses(&memory, None, &extensions), (0..8).collect());
+
+    let mut program = Program::new("1105,1,7,1101,1,1,0,99", || None, |_| {}).with_coverage();
+    program.execute().result().unwrap();
+    let coverage: &Coverage = program.coverage().unwrap();
+    assert_eq!(code_addresses(&memory, Some(coverage), &extensions), vec![0, 1, 2, 7].into_iter().collect());
+}
+
+#[test]
+fn display() {
+    let code: BTreeSet<Position> = (0..4).collect();
+    let diff = diff_memory(&[1, 9, 10, 3, 0, 0], &[3500, 9, 10, 70, 0, -5], &code);
+    assert_eq!(diff.to_string(), "           0..1 code: 1 -> 3500\n           3..4 code: 3 -> 70\n           5..6 data: 0 -> -5\n");
+}
@@ -0,0 +1,105 @@
+use nine1::compiler::compile;
+use nine1::{loader, Integer, Machine, Memory, State, PAGE_SIZE};
+
+/// Next value from a linear congruential generator, so the random writes are the same every run
+fn next_random(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *seed >> 33
+}
+
+#[test]
+fn writes_never_leak_between_forks() {
+    let mut original = Memory::new((0..3 * PAGE_SIZE as Integer).collect());
+    let mut forks = vec![original.clone(); 8];
+//    Each fork gets its own model of what it should hold, starting from the original's values
+    let mut models: Vec<Vec<Integer>> = vec![original.to_vec(); forks.len()];
+    let mut seed = 42;
+    for _ in 0..10000 {
+        let which = next_random(&mut seed) as usize % forks.len();
+        let pos = next_random(&mut seed) as usize % (6 * PAGE_SIZE);
+        let val = next_random(&mut seed) as Integer - (1 << 30);
+        forks[which].set(pos, val);
+        if models[which].len() <= pos {
+            models[which].resize(pos + 1, 0);
+        }
+        models[which][pos] = val;
+    }
+    for (fork, model) in forks.iter().zip(models.iter()) {
+        assert!(model.iter().enumerate().all(|(pos, &val)| fork[pos] == val));
+        assert!((model.len()..8 * PAGE_SIZE).all(|pos| fork[pos] == 0));
+    }
+    assert_eq!(original.to_vec(), (0..3 * PAGE_SIZE as Integer).collect::<Vec<_>>());
+
+    original.set(5, -5);
+    assert!(forks.iter().all(|fork| fork[5] != -5));
+}
+
+#[test]
+fn forks_share_pages_until_written() {
+    let memory = Memory::new(vec![1; 2 * PAGE_SIZE + 1]);
+    assert_eq!(memory.allocated_pages(), 3);
+    let mut fork = memory.clone();
+    assert_eq!(memory.shared_pages(&fork), 3);
+
+    fork.set(PAGE_SIZE, 2);
+    fork.set(PAGE_SIZE + 1, 3);
+    assert_eq!(memory.shared_pages(&fork), 2);
+    assert_eq!((memory[PAGE_SIZE], fork[PAGE_SIZE], fork[PAGE_SIZE - 1]), (1, 2, 1));
+
+    let mut second = fork.clone();
+    second.set(0, 4);
+    assert_eq!((memory.shared_pages(&second), fork.shared_pages(&second)), (1, 2));
+    assert_eq!((memory[0], fork[0], second[0]), (1, 1, 4));
+}
+
+#[test]
+fn zero_pages_are_not_allocated() {
+    let mut memory = Memory::new(vec![0; 3 * PAGE_SIZE]);
+    assert_eq!(memory.allocated_pages(), 0);
+    memory.set(10 * PAGE_SIZE, 0);
+    assert_eq!(memory.allocated_pages(), 0);
+    memory.set(10 * PAGE_SIZE, 9);
+    assert_eq!(memory.allocated_pages(), 1);
+    assert_eq!((memory.used_len(), memory[10 * PAGE_SIZE]), (10 * PAGE_SIZE + 1, 9));
+
+    memory.set(10 * PAGE_SIZE, 0);
+    assert_eq!((memory.used_len(), memory.to_vec()), (0, vec![]));
+    assert_eq!(memory.len(), 10000000);
+    assert_eq!(memory[memory.len() - 1], 0);
+}
+
+#[test]
+#[should_panic(expected = "Address 10000000 is past the end of memory")]
+fn reading_past_the_end() {
+    Memory::new(vec![]).get(10000000);
+}
+
+#[test]
+fn machine_forks_keep_their_own_memory() {
+//    Each fork keeps count of the inputs it has read in the program's memory
+    let intcode = compile("
+        let step = 0;
+        while 1 {
+            let val = input();
+            step = step + 1;
+            output(val * step);
+        }
+    ").unwrap();
+    let mut machine = Machine::new(loader::parse(&intcode).unwrap());
+    assert_eq!(machine.resume(), Ok(State::NeedsInput));
+
+    let mut forks: Vec<Machine> = (0..100).map(|_| machine.clone()).collect();
+    for (i, fork) in forks.iter_mut().enumerate() {
+        for _ in 0..=i % 3 {
+            fork.push_input(i as Integer);
+            assert!(matches!(fork.resume(), Ok(State::Output(_))));
+        }
+    }
+    for (i, fork) in forks.iter_mut().enumerate() {
+        fork.push_input(1);
+        assert_eq!(fork.resume(), Ok(State::Output(i as Integer % 3 + 2)));
+        assert!(fork.memory().shared_pages(machine.memory()) < machine.memory().allocated_pages());
+    }
+    machine.push_input(1);
+    assert_eq!(machine.resume(), Ok(State::Output(1)));
+}
@@ -0,0 +1,202 @@
+use std::cell::RefCell;
+
+use nine1::optimizer::{optimize, Change};
+use nine1::{IntcodeError, Integer, Program};
+
+fn run(memory: &[Integer], inputs: &[Integer]) -> (Result<Option<Integer>, IntcodeError>, Vec<Integer>, u64) {
+    let intcode = memory.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(",");
+    let inputs = RefCell::new(inputs.iter().cloned());
+    let outputs = RefCell::new(Vec::new());
+    let mut program = Program::new(&intcode, || inputs.borrow_mut().next(), |val| outputs.borrow_mut().push(val))
+        .with_cycle_limit(1_000_000);
+    let result = program.execute().result();
+    let cycles = program.cycles();
+    (result, outputs.into_inner(), cycles)
+}
+
+fn parse(intcode: &str) -> Vec<Integer> {
+    intcode.trim().split(',').map(|val| val.parse().unwrap()).collect()
+}
+
+fn assert_same_behaviour(memory: &[Integer], inputs: &[Integer]) {
+    let optimized = optimize(memory);
+    let (result, outputs, cycles) = run(memory, inputs);
+    let (opt_result, opt_outputs, opt_cycles) = run(&optimized.memory, inputs);
+    assert_eq!((opt_result, opt_outputs), (result, outputs), "{:?} with inputs {:?}", memory, inputs);
+    assert!(opt_cycles <= cycles);
+}
+
+#[test]
+fn folds_immediates() {
+    let optimized = optimize(&[1101, 2, 3, 7, 4, 7, 99, 0]);
+    assert_eq!(optimized.memory, vec![1101, 5, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimized.changes, vec![Change::Folded { pos: 0, value: 5 }]);
+
+    assert_eq!(optimize(&[1102, 6, 7, 7, 4, 7, 99, 0]).memory, vec![1101, 42, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimize(&[1107, 2, 3, 7, 4, 7, 99, 0]).memory, vec![1101, 1, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimize(&[1107, 3, 2, 7, 4, 7, 99, 0]).memory, vec![1101, 0, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimize(&[1108, 3, 3, 7, 4, 7, 99, 0]).memory, vec![1101, 1, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimize(&[1108, 3, 4, 7, 4, 7, 99, 0]).memory, vec![1101, 0, 0, 7, 4, 7, 99, 0]);
+}
+
+#[test]
+fn already_folded_code_is_unchanged() {
+    let optimized = optimize(&[1101, 5, 0, 7, 4, 7, 99, 0]);
+    assert_eq!(optimized.changes, vec![]);
+    assert_eq!(optimized.not_optimized, None);
+}
+
+#[test]
+fn overflow_is_not_folded() {
+    let memory = [1102, Integer::MAX, 2, 7, 4, 7, 99, 0];
+    assert_eq!(optimize(&memory).changes, vec![]);
+}
+
+#[test]
+fn identities_become_copies() {
+    let optimized = optimize(&[1002, 7, 1, 8, 4, 8, 99, 42, 0]);
+    assert_eq!(optimized.memory, vec![1001, 7, 0, 8, 4, 8, 99, 42, 0]);
+    assert_eq!(optimized.changes, vec![Change::Copy { pos: 0 }]);
+
+    assert_eq!(optimize(&[102, 1, 7, 8, 4, 8, 99, 42, 0]).memory, vec![1001, 7, 0, 8, 4, 8, 99, 42, 0]);
+    assert_eq!(optimize(&[101, 0, 7, 8, 4, 8, 99, 42, 0]).memory, vec![1001, 7, 0, 8, 4, 8, 99, 42, 0]);
+
+    let optimized = optimize(&[1002, 7, 0, 8, 4, 8, 99, 42, 0]);
+    assert_eq!(optimized.memory, vec![1101, 0, 0, 8, 4, 8, 99, 42, 0]);
+    assert_eq!(optimized.changes, vec![Change::Folded { pos: 0, value: 0 }]);
+}
+
+#[test]
+fn multiply_by_zero_keeps_reads_which_fail() {
+    let memory = [1002, -1, 0, 8, 4, 8, 99, 42, 0];
+    assert_eq!(optimize(&memory).changes, vec![]);
+    assert_same_behaviour(&memory, &[]);
+}
+
+#[test]
+fn threads_jumps_through_unconditional_jumps() {
+    let memory = [1105, 1, 5, 104, 0, 1105, 1, 9, 99, 104, 7, 99];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.changes, vec![Change::Threaded { pos: 0, from: 5, to: 9 }]);
+    assert_eq!(optimized.memory[2], 9);
+    assert_same_behaviour(&memory, &[]);
+}
+
+#[test]
+fn removes_dead_jumps_which_are_skipped() {
+    let memory = [1105, 1, 3, 1106, 1, 9, 104, 1, 99];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.changes, vec![
+        Change::Threaded { pos: 0, from: 3, to: 6 },
+        Change::RemovedDeadJump { pos: 3 },
+    ]);
+    assert_eq!(optimized.memory, vec![1105, 1, 6, 0, 0, 0, 104, 1, 99]);
+    assert_same_behaviour(&memory, &[]);
+}
+
+#[test]
+fn keeps_dead_jumps_which_can_be_reached() {
+    let optimized = optimize(&[1106, 1, 9, 104, 2, 99]);
+    assert_eq!(optimized.changes, vec![]);
+    let optimized = optimize(&[104, 1, 1105, 0, 9, 104, 2, 99]);
+    assert_eq!(optimized.changes, vec![]);
+}
+
+#[test]
+fn leaves_jump_cycles_alone() {
+    assert_eq!(optimize(&[1105, 1, 3, 1105, 1, 0]).changes, vec![]);
+}
+
+#[test]
+fn leaves_instructions_the_program_reads() {
+//    Output 1 reads the first param of the add, which would change if it was folded
+    let memory = [1101, 2, 3, 9, 4, 1, 4, 9, 99, 0];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.changes, vec![]);
+    assert_eq!(run(&optimized.memory, &[]).1, vec![2, 5]);
+}
+
+#[test]
+fn bails_on_relative_mode() {
+    let memory = [109, 1, 204, -1, 1102, 2, 3, 11, 99];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.memory, memory.to_vec());
+    assert_eq!(optimized.changes, vec![]);
+    assert_eq!(optimized.not_optimized.as_deref(),
+        Some("Instruction at 2 uses relative mode, so any address could be read or written"));
+}
+
+#[test]
+fn bails_on_self_modifying_code() {
+    let memory = parse(include_str!("../aot-tests/programs/day2_example.txt"));
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.memory, memory);
+    assert_eq!(optimized.not_optimized.as_deref(), Some("Program writes to its own code at 0"));
+
+    let memory = parse(include_str!("../aot-tests/programs/day5_param_modes.txt"));
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.memory, memory);
+    assert_eq!(optimized.not_optimized.as_deref(), Some("Program might execute the value it writes to 4"));
+}
+
+#[test]
+fn bails_on_jumps_into_instructions() {
+//    Jumps into the add, so its params run as an output and then an invalid opcode
+    let memory = [1105, 1, 4, 1101, 4, 5, 10, 99, 0, 0, 0];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.memory, memory.to_vec());
+    assert_eq!(optimized.changes, vec![]);
+    assert_eq!(optimized.not_optimized.as_deref(), Some("Program jumps to 4, which isn't the start of an instruction"));
+    assert_eq!(run(&optimized.memory, &[]).1, vec![5]);
+}
+
+#[test]
+fn bails_on_computed_jumps() {
+    //    Jumps to the 9 stored at 12, past the output
+    let memory = [1101, 2, 7, 12, 105, 1, 12, 104, 1, 99, 0, 0, 0];
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.memory, memory.to_vec());
+    assert_eq!(optimized.not_optimized.as_deref(), Some("Jump at 4 has a target which isn't known until it runs"));
+    assert_eq!(run(&optimized.memory, &[]).1, vec![]);
+}
+
+#[test]
+fn change_descriptions() {
+    let descriptions: Vec<String> = [
+        Change::Folded { pos: 0, value: 42 },
+        Change::Copy { pos: 4 },
+        Change::Threaded { pos: 8, from: 11, to: 20 },
+        Change::RemovedDeadJump { pos: 11 },
+    ].iter().map(|change| change.to_string()).collect();
+    assert_eq!(descriptions, vec![
+        "0: folded to 42",
+        "4: simplified to a copy",
+        "8: jump to 11 now goes to 20",
+        "11: removed dead jump",
+    ]);
+}
+
+#[test]
+fn optimized_programs_behave_the_same() {
+    let programs: &[(&str, &[&[Integer]])] = &[
+        (include_str!("../aot-tests/programs/day5_compare_to_8.txt"), &[&[7], &[8], &[9]]),
+        (include_str!("../aot-tests/programs/day5_jumps.txt"), &[&[0], &[5]]),
+        (include_str!("../aot-tests/programs/day9_quine.txt"), &[&[]]),
+        (include_str!("../aot-tests/programs/boost.txt"), &[&[1]]),
+        (include_str!("../aot-tests/programs/negative_address.txt"), &[&[]]),
+        (include_str!("../aot-tests/programs/immediate_write.txt"), &[&[]]),
+    ];
+    for (intcode, inputs) in programs {
+        for inputs in inputs.iter() {
+            assert_same_behaviour(&parse(intcode), inputs);
+        }
+    }
+}
+
+#[test]
+fn day5_compare_to_8_is_optimized() {
+    let memory = parse(include_str!("../aot-tests/programs/day5_compare_to_8.txt"));
+    let optimized = optimize(&memory);
+    assert_eq!(optimized.not_optimized, None);
+    assert_eq!(optimized.changes, vec![Change::Folded { pos: 36, value: 1001 }]);
+}
@@ -0,0 +1,67 @@
+use std::iter;
+
+use nine1::{IntcodeError, Integer, Program, State};
+
+const QUINE: &str = include_str!("../aot-tests/programs/day9_quine.txt");
+const COMPARE_TO_8: &str = include_str!("../aot-tests/programs/day5_compare_to_8.txt");
+
+fn program(intcode: &str) -> Program<impl Fn() -> Option<Integer>, impl Fn(Integer)> {
+    Program::new(intcode.trim(), || panic!("Input function used"), |_| panic!("Output function used"))
+}
+
+#[test]
+fn collects_every_output() {
+    let mut quine = program(QUINE);
+    let outputs = quine.outputs(iter::empty()).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(outputs, nine1::loader::parse(QUINE).unwrap());
+    assert!(quine.machine().is_halted());
+}
+
+#[test]
+fn inputs_are_read_in_order() {
+    let mut program = program(COMPARE_TO_8);
+    for &(input, expected) in [(7, 999), (8, 1000), (9, 1001)].iter() {
+        assert_eq!(program.outputs(iter::once(input)).collect::<Vec<_>>(), vec![Ok(expected)]);
+    }
+//    Adds up pairs of inputs
+    let mut adder = Program::new("3,20,3,21,1,20,21,22,4,22,1105,1,0", || None, |_| {});
+    let outputs = adder.outputs(vec![1, 2, 10, 20, 5]).collect::<Vec<_>>();
+    assert_eq!(outputs, vec![Ok(3), Ok(30), Err(IntcodeError::InputExhausted { pos: 2 })]);
+}
+
+#[test]
+fn stopping_pauses_execution() {
+    let mut quine = program(QUINE);
+    {
+        let mut outputs = quine.outputs(iter::empty());
+        assert_eq!(outputs.next(), Some(Ok(109)));
+        assert_eq!(outputs.next(), Some(Ok(1)));
+    }
+    let cycles = quine.cycles();
+    assert!(!quine.machine().is_halted());
+
+    let mut machine = quine.into_machine();
+    let rest = machine.outputs(iter::empty()).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rest.len(), 14);
+    assert!(machine.cycles() > cycles);
+    assert_eq!(machine.resume(), Ok(State::Halted));
+}
+
+#[test]
+fn restarts_from_the_beginning() {
+    let mut adder = Program::new("3,20,3,21,1,20,21,22,4,22,1105,1,0", || None, |_| {});
+    assert_eq!(adder.outputs(vec![1, 2, 3]).next(), Some(Ok(3)));
+    assert_eq!(adder.outputs(vec![4, 5]).next(), Some(Ok(9)));
+    assert_eq!(adder.cycles(), 4);
+}
+
+#[test]
+fn ends_after_an_error() {
+    let mut program = program("104,1,104,2,1105,1,-1");
+    let mut outputs = program.outputs(iter::empty());
+    assert_eq!(outputs.next(), Some(Ok(1)));
+    assert_eq!(outputs.next(), Some(Ok(2)));
+    assert_eq!(outputs.next(), Some(Err(IntcodeError::NegativeAddress { pos: 4, address: -1 })));
+    assert_eq!(outputs.next(), None);
+    assert_eq!(outputs.next(), None);
+}
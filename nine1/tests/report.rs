@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+
+use nine1::{Extension, ExtensionAction, Extensions, HaltReason, Instruction, IntcodeError, Program, Stats};
+
+const BOOST: &str = include_str!("../aot-tests/programs/boost.txt");
+
+#[test]
+fn boost_self_test() {
+    let mut program = Program::new(BOOST.trim(), || Some(1), |_| {});
+    let report = program.execute();
+    assert_eq!(report.halt, HaltReason::Halt);
+    let stats = &report.stats;
+    assert_eq!((stats.inputs, stats.outputs), (1, 1));
+    assert_eq!(stats.instructions.values().sum::<u64>(), stats.cycles);
+    assert_eq!(stats.cycles, program.cycles());
+    assert_eq!(stats.instructions[&Instruction::Halt], 1);
+    assert!(stats.extensions.is_empty());
+    assert_eq!(stats.min_relative_base, 0);
+    assert!(stats.max_relative_base > 1000);
+    assert!(stats.highest_address.unwrap() as usize > BOOST.split(',').count());
+    assert_eq!(report.result(), Ok(stats.last_output));
+}
+
+#[test]
+fn highest_address_touched() {
+    let mut program = Program::new("1101,1,1,5000,99", || None, |_| {});
+    assert_eq!(program.execute().stats.highest_address, Some(5000));
+    let mut program = Program::new("1,7000,0,0,99", || None, |_| {});
+    assert_eq!(program.execute().stats.highest_address, Some(7000));
+}
+
+#[test]
+fn relative_base_range() {
+    let mut program = Program::new("109,10,109,-25,109,5,99", || None, |_| {});
+    let stats = program.execute().stats;
+    assert_eq!((stats.min_relative_base, stats.max_relative_base), (-15, 10));
+    assert_eq!(stats.instructions[&Instruction::RelativeBaseOffset], 3);
+}
+
+#[test]
+fn halt_reasons() {
+    let mut program = Program::new("3,9,4,9,99", || None, |_| {});
+    let report = program.execute();
+    assert_eq!(report.halt, HaltReason::Error(IntcodeError::InputExhausted { pos: 0 }));
+    assert_eq!((report.stats.cycles, report.stats.inputs), (1, 0));
+    assert_eq!(report.result(), Err(IntcodeError::InputExhausted { pos: 0 }));
+
+    let mut program = Program::new("104,3,1105,1,0", || None, |_| {}).with_cycle_limit(10);
+    let report = program.execute();
+    assert_eq!(report.halt, HaltReason::Error(IntcodeError::CycleLimit { pos: 0, cycles: 10 }));
+    assert_eq!((report.stats.outputs, report.stats.last_output), (5, Some(3)));
+
+    let mut extensions = Extensions::new();
+    extensions.register(50, Extension::new("exit", 1, |params| ExtensionAction::Halt(params[0]))).unwrap();
+    let mut program = Program::new("104,8,1150,3,99", || None, |_| {}).with_extensions(extensions);
+    let report = program.execute();
+    assert_eq!(report.halt, HaltReason::Extension(3));
+    assert_eq!((report.stats.extensions[&50], report.stats.instructions.get(&Instruction::Halt)), (1, None));
+    assert_eq!(report.result(), Ok(Some(8)));
+}
+
+#[test]
+fn stats_start_again_each_run() {
+    let inputs = RefCell::new(vec![2, 1]);
+    let mut program = Program::new("3,9,4,9,99", || inputs.borrow_mut().pop(), |_| {});
+    let first = program.execute();
+    let second = program.execute();
+    assert_eq!(second.stats, Stats { last_output: Some(2), ..first.stats.clone() });
+    assert_eq!((second.stats.cycles, second.stats.inputs), (3, 1));
+    assert_eq!(first.to_string(), "Halted\nCycles: 3\n  Input: 1\n  Output: 1\n  Halt: 1\nHighest address: 9\n\
+        Relative base: 0 to 0\nInputs: 1, outputs: 1");
+}
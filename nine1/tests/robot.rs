@@ -0,0 +1,75 @@
+use nine1::compiler::compile;
+use nine1::robot::{self, Colour, Direction, Robot};
+
+/// Brain which makes the moves from the example on day 11, ignoring its camera
+const EXAMPLE: &str = "3,100,104,1,104,0,3,100,104,0,104,0,3,100,104,1,104,0,3,100,104,1,104,0,\
+    3,100,104,0,104,1,3,100,104,1,104,0,3,100,104,1,104,0,99";
+
+/// Brain which paints every panel the opposite colour to what it sees and turns right
+const INVERTER: &str = "3,100,1002,100,-1,100,1001,100,1,100,4,100,104,1,1105,1,0";
+
+#[test]
+fn day11_example() {
+    let robot = robot::run(EXAMPLE, Colour::Black, None).unwrap();
+    assert_eq!(robot.painted(), 6);
+    assert_eq!(robot.moves(), 7);
+    assert_eq!((robot.pos(), robot.direction()), ((0, -1), Direction::Left));
+    assert_eq!(robot.colour((0, 0)), Colour::Black);
+    assert_eq!(robot.colour((1, -1)), Colour::White);
+    assert_eq!(robot.render(), "  █\n▒▒█\n██ ");
+}
+
+#[test]
+fn brain_sees_the_panel_colours() {
+    let robot = robot::run(INVERTER, Colour::Black, Some(4)).unwrap();
+    assert_eq!(robot.render(), "██\n██");
+    let robot = robot::run(INVERTER, Colour::Black, Some(8)).unwrap();
+    assert_eq!(robot.render(), "▒▒\n▒▒");
+    assert_eq!(robot.painted(), 4);
+    assert_eq!(robot.pos(), (0, 0));
+}
+
+#[test]
+fn starting_on_white() {
+    assert_eq!(Robot::new(Colour::White).painted(), 0);
+//    The starting panel isn't painted by the brain if it halts straight away
+    let robot = robot::run("99", Colour::White, None).unwrap();
+    assert_eq!((robot.painted(), robot.camera()), (0, Colour::White));
+    assert_eq!(robot.render(), "█");
+    let robot = robot::run(INVERTER, Colour::White, Some(1)).unwrap();
+    assert_eq!(robot.render(), "▒");
+    assert_eq!((robot.painted(), robot.pos()), (1, (1, 0)));
+}
+
+#[test]
+fn compiled_brain() {
+//    Goes round a 2x2 square one and a half times, inverting the panels
+    let brain = compile("
+        let n = 0;
+        while n < 6 {
+            output(1 - input());
+            output(1);
+            n = n + 1;
+        }
+    ").unwrap();
+    let robot = robot::run(&brain, Colour::Black, None).unwrap();
+    assert_eq!(robot.render(), "▒▒\n██");
+    assert_eq!((robot.painted(), robot.pos(), robot.direction()), (4, (1, 1), Direction::Down));
+}
+
+#[test]
+fn unknown_outputs_are_ignored() {
+    let mut robot = Robot::new(Colour::Black);
+    robot.push(7);
+    robot.push(1);
+    assert_eq!((robot.painted(), robot.pos(), robot.direction()), (0, (1, 0), Direction::Right));
+    robot.push(1);
+    robot.push(5);
+    assert_eq!((robot.painted(), robot.pos(), robot.direction()), (1, (2, 0), Direction::Right));
+}
+
+#[test]
+fn errors_from_the_brain() {
+    assert!(robot::run("3,100,104,1,104,1,42", Colour::Black, None).is_err());
+    assert_eq!(robot::run("99", Colour::Black, None).unwrap().render(), "");
+}
@@ -0,0 +1,103 @@
+use nine1::scheduler::{Finished, MachineId, Policy, Scheduler, SchedulerError};
+use nine1::{loader, IntcodeError, Integer, Machine};
+
+/// Feedback loop examples from day 7, with their best phase settings and the signal they give
+const FEEDBACK_LOOPS: [(&str, [Integer; 5], Integer); 2] = [
+    ("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+     [9, 8, 7, 6, 5], 139629729),
+    ("3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,\
+      1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+     [9, 7, 8, 5, 6], 18216),
+];
+
+/// Reads a value and outputs it, forever
+const ECHO: &str = "3,20,4,20,1105,1,0";
+
+fn machine(intcode: &str) -> Machine {
+    Machine::new(loader::parse(intcode).unwrap())
+}
+
+fn amplifiers(intcode: &str, phases: &[Integer], policy: Policy) -> Scheduler {
+    let mut scheduler = Scheduler::new(policy);
+    let ids: Vec<MachineId> = phases.iter().map(|_| scheduler.add(machine(intcode))).collect();
+    for (i, &phase) in phases.iter().enumerate() {
+        scheduler.connect(ids[i], ids[(i + 1) % ids.len()]);
+        scheduler.send(ids[i], phase);
+    }
+    scheduler.send(ids[0], 0);
+    scheduler
+}
+
+#[test]
+fn amplifier_feedback_loops() {
+    for &(intcode, phases, signal) in FEEDBACK_LOOPS.iter() {
+        for &policy in [Policy::RoundRobin, Policy::EventOrder].iter() {
+            let mut scheduler = amplifiers(intcode, &phases, policy);
+            assert_eq!(scheduler.run(), Ok(Finished { last_halted: 4, final_output: Some(signal) }));
+//            The last signal goes back round to the first amplifier, which has already halted
+            assert_eq!(scheduler.inbox(0).collect::<Vec<_>>(), vec![signal]);
+            assert!((0..5).all(|id| scheduler.machine(id).is_halted()));
+        }
+    }
+}
+
+#[test]
+fn policies_order_values_differently() {
+    let outputs_of_sink = |policy| {
+        let mut scheduler = Scheduler::new(policy);
+        let first = scheduler.add(machine("104,1,104,2,99"));
+        let second = scheduler.add(machine("104,10,104,20,99"));
+        let sink = scheduler.add(machine(ECHO));
+        scheduler.connect(first, sink);
+        scheduler.connect(second, sink);
+        assert_eq!(scheduler.run(), Err(SchedulerError::Deadlock { waiting: vec![sink] }));
+        scheduler.outputs(sink).to_vec()
+    };
+    assert_eq!(outputs_of_sink(Policy::RoundRobin), vec![1, 2, 10, 20]);
+    assert_eq!(outputs_of_sink(Policy::EventOrder), vec![1, 10, 2, 20]);
+}
+
+#[test]
+fn deadlock() {
+    let mut scheduler = Scheduler::new(Policy::EventOrder);
+    let first = scheduler.add(machine(ECHO));
+    let second = scheduler.add(machine(ECHO));
+    scheduler.connect(first, second);
+    scheduler.connect(second, first);
+    let err = scheduler.run().unwrap_err();
+    assert_eq!(err, SchedulerError::Deadlock { waiting: vec![first, second] });
+    assert_eq!(err.to_string(), "Deadlock with machines [0, 1] waiting for input");
+
+    let mut scheduler = Scheduler::new(Policy::RoundRobin);
+    let echo = scheduler.add(machine(ECHO));
+    scheduler.add(machine("99"));
+    scheduler.send(echo, 7);
+    assert_eq!(scheduler.run(), Err(SchedulerError::Deadlock { waiting: vec![echo] }));
+    assert_eq!(scheduler.outputs(echo), &[7]);
+}
+
+#[test]
+fn machine_errors() {
+    let mut scheduler = Scheduler::new(Policy::RoundRobin);
+    scheduler.add(machine("99"));
+    let broken = scheduler.add(machine("104,3,42"));
+    let err = scheduler.run().unwrap_err();
+    assert_eq!(err, SchedulerError::Machine { id: broken, error: IntcodeError::UnknownOpcode { pos: 2, opcode: 42 } });
+    assert_eq!(err.to_string(), "Machine 1: Unknown opcode 42 at pos 2");
+    assert_eq!(scheduler.outputs(broken), &[3]);
+}
+
+#[test]
+fn last_machine_to_halt() {
+    let mut scheduler = Scheduler::new(Policy::EventOrder);
+    let slow = scheduler.add(machine("3,20,104,7,104,8,99"));
+    let fast = scheduler.add(machine("104,1,99"));
+    scheduler.connect(fast, slow);
+    assert_eq!(scheduler.run(), Ok(Finished { last_halted: slow, final_output: Some(8) }));
+
+    let mut halted = machine("99");
+    halted.resume().unwrap();
+    let mut scheduler = Scheduler::new(Policy::RoundRobin);
+    scheduler.add(halted);
+    assert_eq!(scheduler.run(), Ok(Finished { last_halted: 0, final_output: None }));
+}
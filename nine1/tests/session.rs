@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+
+use nine1::{replay, Divergence, Event, IntcodeError, Program, Session};
+
+/// Reads a value and outputs one more than it
+const ADD_ONE: &str = "3,9,1001,9,1,10,4,10,99,0,0";
+
+fn parse(intcode: &str) -> Vec<i64> {
+    nine1::loader::parse(intcode).unwrap()
+}
+
+fn record(intcode: &str, inputs: Vec<i64>) -> Session {
+    let inputs = RefCell::new(inputs.into_iter());
+    let mut program = Program::new(intcode, || inputs.borrow_mut().next(), |_| {}).with_recording();
+    program.execute().result().unwrap();
+    program.session().unwrap().clone()
+}
+
+#[test]
+fn records_events() {
+    let session = record(ADD_ONE, vec![5]);
+    assert_eq!(session.events, vec![
+        Event::Input { cycle: 1, pos: 0, value: 5 },
+        Event::Output { cycle: 3, pos: 6, value: 6 },
+        Event::Halt { cycle: 4, pos: 8 },
+    ]);
+    assert_eq!(session.inputs().collect::<Vec<_>>(), vec![5]);
+    assert_eq!(Program::new(ADD_ONE, || None, |_| {}).session(), None);
+}
+
+#[test]
+fn text_round_trip() {
+    let session = record(ADD_ONE, vec![5]);
+    let mut text = Vec::new();
+    session.write_to(&mut text).unwrap();
+    assert_eq!(String::from_utf8(text.clone()).unwrap(), "in 1 0 5\nout 3 6 6\nhalt 4 8\n");
+    assert_eq!(Session::read_from(&text[..]).unwrap(), session);
+    assert_eq!(Session::read_from("\nin 1 0 -5\n\n".as_bytes()).unwrap().inputs().collect::<Vec<_>>(), vec![-5]);
+
+    let path = std::env::temp_dir().join(format!("nine1-session-{}.txt", std::process::id()));
+    session.save(&path).unwrap();
+    let loaded = Session::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), session);
+}
+
+#[test]
+fn invalid_events() {
+    for line in ["in 1 0", "halt 4 8 1", "out x 6 6", "jump 1 2 3"].iter() {
+        let err = Session::read_from(line.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("Invalid session event: {}", line));
+    }
+}
+
+#[test]
+fn matching_replay() {
+    assert_eq!(replay(&parse(ADD_ONE), &record(ADD_ONE, vec![5])), Ok(()));
+    let boost = include_str!("../aot-tests/programs/boost.txt").trim();
+    assert_eq!(replay(&parse(boost), &record(boost, vec![1])), Ok(()));
+}
+
+#[test]
+fn output_mismatch() {
+    let session = record(ADD_ONE, vec![5]);
+//    Adds 2 instead of 1
+    let divergence = replay(&parse("3,9,1001,9,2,10,4,10,99,0,0"), &session).unwrap_err();
+    assert_eq!(divergence, Divergence {
+        cycle: 3,
+        pos: 6,
+        expected: Some(Event::Output { cycle: 3, pos: 6, value: 6 }),
+        actual: Some(Event::Output { cycle: 3, pos: 6, value: 7 }),
+        error: None,
+    });
+    assert_eq!(divergence.to_string(), "Diverged at cycle 3, pos 6: expected `out 3 6 6` but got `out 3 6 7`");
+}
+
+#[test]
+fn replay_which_stops_early() {
+//    Goes back to read another input instead of outputting again
+    let session = record("3,11,4,11,4,11,99,0,0,0,0,0", vec![5]);
+    let divergence = replay(&parse("3,11,4,11,1105,1,0,0,0,0,0,0"), &session).unwrap_err();
+    assert_eq!(divergence, Divergence {
+        cycle: 4,
+        pos: 0,
+        expected: Some(Event::Output { cycle: 3, pos: 4, value: 5 }),
+        actual: None,
+        error: Some(IntcodeError::InputExhausted { pos: 0 }),
+    });
+    assert_eq!(divergence.to_string(),
+        "Diverged at cycle 4, pos 0: expected `out 3 4 5` but the program stopped (Ran out of input for instruction at pos 0)");
+}
+
+#[test]
+fn replay_which_goes_elsewhere() {
+    let session = record("104,1,99", vec![]);
+    let divergence = replay(&parse("104,1,104,2,99"), &session).unwrap_err();
+    assert_eq!(divergence.expected, Some(Event::Halt { cycle: 2, pos: 2 }));
+    assert_eq!(divergence.actual, Some(Event::Output { cycle: 2, pos: 2, value: 2 }));
+    assert_eq!(divergence.to_string(), "Diverged at cycle 2, pos 2: expected `halt 2 2` but got `out 2 2 2`");
+}
@@ -0,0 +1,30 @@
+use std::ops::Range;
+
+/// Addresses written to since the last `take`, kept as sorted, non-adjacent ranges
+#[derive(Debug, Default)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<u32>>,
+}
+
+impl DirtyRanges {
+    pub fn mark(&mut self, pos: u32) {
+//        First range which contains pos or could be extended to it
+        let i = self.ranges.partition_point(|range| range.end < pos);
+        match self.ranges.get_mut(i) {
+            Some(range) if range.contains(&pos) => {}
+            Some(range) if range.end == pos => {
+                range.end += 1;
+                if self.ranges.get(i + 1).map(|next| next.start) == Some(pos + 1) {
+                    let next = self.ranges.remove(i + 1);
+                    self.ranges[i].end = next.end;
+                }
+            }
+            Some(range) if range.start == pos + 1 => range.start = pos,
+            _ => self.ranges.insert(i, pos..pos + 1),
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<Range<u32>> {
+        std::mem::take(&mut self.ranges)
+    }
+}
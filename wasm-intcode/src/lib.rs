@@ -1,3 +1,4 @@
+mod dirty;
 mod utils;
 
 use wasm_bindgen::prelude::*;
@@ -9,23 +10,33 @@ use wasm_bindgen::prelude::*;
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 type Position = u32;
-type Integer = i32;
-type InputFn = Box<dyn FnMut(u32) -> Integer>;
+type Integer = i64;
+
+/// Memory grows on demand when written past its end, up to this many cells
+const MAX_MEMORY: usize = 1 << 24;
 
 struct Program {
     data: Vec<Integer>,
-    input_fn: InputFn,
-    input_call_count: u32
+    pos: Position,
+    relative_base: Integer,
+    inputs: VecDeque<Integer>,
+    outputs: Vec<Integer>,
+    halted: bool,
+    dirty: DirtyRanges,
 }
 
+use dirty::DirtyRanges;
 use num_enum::TryFromPrimitive;
+use std::collections::VecDeque;
 use std::convert::{TryFrom, TryInto};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, TryFromPrimitive)]
 #[repr(u8)]
 enum ParamMode {
     POSITION = 0,
     IMMEDIATE = 1,
+    RELATIVE = 2,
 }
 
 const NUM_PARAMS: usize = 3;
@@ -43,144 +54,364 @@ enum Instruction {
     JumpIfFalse = 6,
     LessThan = 7,
     Equals = 8,
+    RelativeBaseOffset = 9,
     Halt = 99,
 }
 
+/// What happened during a step of a `Machine`
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// An instruction was executed which didn't do any I/O
+    Stepped = 0,
+    /// The next instruction is Input but there is no input to give it, see `push_input`
+    NeedsInput = 1,
+    /// An output was produced, see `take_outputs`
+    Output = 2,
+    Halted = 3,
+}
+
+/// Error from running a program, with the position and opcode of the failing instruction.
+/// Thrown to JS as an `Error` with `pc` and `opcode` properties, `opcode` being a BigInt like
+/// every other memory value.
+#[derive(Debug, Clone, PartialEq)]
+struct VmError {
+    pc: Position,
+    opcode: Integer,
+    reason: String,
+}
+
+impl From<VmError> for JsValue {
+    fn from(err: VmError) -> JsValue {
+        let message = format!("{} (opcode {} at pc {})", err.reason, err.opcode, err.pc);
+        let js_err = js_sys::Error::new(&message);
+        js_sys::Reflect::set(&js_err, &"pc".into(), &err.pc.into()).unwrap();
+        js_sys::Reflect::set(&js_err, &"opcode".into(), &err.opcode.into()).unwrap();
+        js_err.into()
+    }
+}
+
+fn parse_error(message: String) -> JsValue {
+    js_sys::Error::new(&message).into()
+}
+
+/// Value returned by an input function: a BigInt, or a Number if it's a safe integer
+fn input_value(value: &JsValue) -> Option<Integer> {
+    if value.is_bigint() {
+        return Integer::try_from(value.clone()).ok();
+    }
+    const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+    value.as_f64()
+        .filter(|val| val.fract() == 0.0 && val.abs() <= MAX_SAFE_INTEGER)
+        .map(|val| val as Integer)
+}
+
+/// Run the program with 1 as every input and return the last output
+#[wasm_bindgen]
+pub fn run_program(code: &str) -> Result<Integer, JsValue> {
+    let mut program = Program::new(code).map_err(parse_error)?;
+    program.execute(|_| 1)?
+        .ok_or_else(|| js_sys::Error::new("Program halted without any output").into())
+}
+
+/// Run the program, calling `input` with the number of the input (starting at 1) whenever it
+/// needs input, and return the last output
+#[wasm_bindgen]
+pub fn run_program_with_input(code: &str, input: &js_sys::Function) -> Result<Option<Integer>, JsValue> {
+    run_with_callbacks(code, input, None)
+}
+
+/// Like `run_program_with_input`, also calling `output` with every output value as it's produced
 #[wasm_bindgen]
-pub fn run_program(code: &str) -> Integer {
-    let mut program = Program::new(code, Box::new(|_| 1));
-    program.execute().unwrap()
+pub fn run_program_with_io(code: &str, input: &js_sys::Function, output: &js_sys::Function) -> Result<Option<Integer>, JsValue> {
+    run_with_callbacks(code, input, Some(output))
 }
 
-fn read_opcode(param: Integer) -> (Instruction, ParamModes) {
-    let a = ((param / 10000) % 1000) as u8;
-    let b = ((param / 1000) % 100) as u8;
-    let c = ((param / 100) % 10) as u8;
-//        TODO: Remove ugly use of unwrap. Should use ? to return Result's instead?
+fn run_with_callbacks(code: &str, input: &js_sys::Function, output: Option<&js_sys::Function>) -> Result<Option<Integer>, JsValue> {
+    let mut program = Program::new(code).map_err(parse_error)?;
+    let mut input_call_count: u32 = 0;
+    let mut prev_output = None;
+    loop {
+        match program.step()? {
+            Event::NeedsInput => {
+                input_call_count += 1;
+                let value = input.call1(&JsValue::NULL, &input_call_count.into())?;
+                let value = input_value(&value).ok_or_else(|| program.error(
+                    format!("Input function returned {:?} instead of an integer", value)))?;
+                program.inputs.push_back(value);
+            }
+            Event::Output => {
+                for value in program.outputs.drain(..) {
+                    if let Some(output) = output {
+                        output.call1(&JsValue::NULL, &value.into())?;
+                    }
+                    prev_output = Some(value);
+                }
+            }
+            Event::Halted => return Ok(prev_output),
+            Event::Stepped => {}
+        }
+    }
+}
+
+/// Intcode VM which JS can run one instruction at a time. Memory values, inputs and outputs are
+/// BigInts.
+#[wasm_bindgen]
+pub struct Machine {
+    program: Program,
+}
+
+#[wasm_bindgen]
+impl Machine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(code: &str) -> Result<Machine, JsValue> {
+        utils::set_panic_hook();
+        Ok(Machine {
+            program: Program::new(code).map_err(parse_error)?,
+        })
+    }
+
+    /// Execute a single instruction, unless waiting for input or halted
+    pub fn step(&mut self) -> Result<Event, JsValue> {
+        Ok(self.program.step()?)
+    }
+
+    /// Execute instructions until one produces an output, input is needed or the program halts
+    pub fn run_until_event(&mut self) -> Result<Event, JsValue> {
+        loop {
+            match self.program.step()? {
+                Event::Stepped => continue,
+                event => return Ok(event),
+            }
+        }
+    }
+
+    pub fn push_input(&mut self, value: Integer) {
+        self.program.inputs.push_back(value);
+    }
+
+    /// Outputs produced since the last call
+    pub fn take_outputs(&mut self) -> Vec<Integer> {
+        std::mem::take(&mut self.program.outputs)
+    }
+
+    pub fn pc(&self) -> Position {
+        self.program.pos
+    }
+
+    pub fn relative_base(&self) -> Integer {
+        self.program.relative_base
+    }
+
+    /// Address of the first memory cell in wasm memory, to be read together with `memory_len`
+    pub fn memory_ptr(&self) -> *const Integer {
+        self.program.data.as_ptr()
+    }
+
+    /// Number of memory cells. Memory grows when the program writes past its end, so this can
+    /// change after any step.
+    pub fn memory_len(&self) -> usize {
+        self.program.data.len()
+    }
+
+    /// View straight into the machine's memory, without copying.
+    ///
+    /// The view is only valid until the next call into this module: anything which allocates can
+    /// grow the wasm memory, which detaches the view. The machine's own memory can also grow and
+    /// move. Get a new view after every run slice.
+    pub fn memory_view(&self) -> js_sys::BigInt64Array {
+        unsafe { js_sys::BigInt64Array::view(&self.program.data) }
+    }
+
+    /// Ranges of memory written to since the last call, as a flat list of start and end
+    /// addresses: `[start0, end0, start1, end1, ...]` with each end exclusive
+    pub fn take_dirty_ranges(&mut self) -> Vec<Position> {
+        self.program.dirty.take().into_iter()
+            .flat_map(|range| vec![range.start, range.end])
+            .collect()
+    }
+}
+
+fn read_opcode(param: Integer) -> Result<(Instruction, ParamModes), String> {
     let last_2_digits = (param % 100) as u8;
-    let instruction = Instruction::try_from(last_2_digits).expect(format!("Last 2 digits of opcode {} are not a valid instruction", last_2_digits).as_str());
+    let instruction = Instruction::try_from(last_2_digits)
+        .map_err(|_| format!("Last 2 digits of opcode {} are not a valid instruction", last_2_digits))?;
+    let read_mode = |digit: Integer| {
+        let mode = (param / digit) % 10;
+        ParamMode::try_from(mode as u8).map_err(|_| format!("Invalid param mode {}", mode))
+    };
     let modes: ParamModes = [
 //            Modes are in reverse order of the parameters :)
-        ParamMode::try_from(c).unwrap(),
-        ParamMode::try_from(b).unwrap(),
-        ParamMode::try_from(a).unwrap(),
+        read_mode(100)?,
+        read_mode(1000)?,
+        read_mode(10000)?,
     ];
-    (instruction, modes)
+    Ok((instruction, modes))
 }
 
 impl Program {
-    pub fn new(intcode: &str, input_fn: InputFn) -> Self {
-        let memory = intcode.split(",").map(
-            |x: &str| x.parse::<Integer>().unwrap()
-        ).collect();
-        Program {
+    pub fn new(intcode: &str) -> Result<Self, String> {
+        let memory = intcode.trim().split(',').map(
+            |x: &str| x.trim().parse::<Integer>().map_err(|_| format!("Invalid value in program: {:?}", x))
+        ).collect::<Result<_, _>>()?;
+        Ok(Program {
             data: memory,
-            input_fn: input_fn,
-            input_call_count: 0,
+            pos: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: Vec::new(),
+            halted: false,
+            dirty: DirtyRanges::default(),
+        })
+    }
+
+    /// Error at the current instruction
+    fn error(&self, reason: String) -> VmError {
+        VmError {
+            pc: self.pos,
+            opcode: self.get(self.pos),
+            reason,
         }
     }
+
+    fn address(val: Integer) -> Result<Position, String> {
+        val.try_into().map_err(|_| format!("Invalid address {}", val))
+    }
+
+    /// Memory past the end reads as 0
     fn get(&self, pos: Position) -> Integer {
-        println!("\t\tRead from pos {}", pos);
-        let val = self.data[pos as usize];
-        println!("\t\tGot {}", val);
-        val
+        self.data.get(pos as usize).cloned().unwrap_or(0)
     }
-    fn read_param(&self, pos: Position, mode: &ParamMode) -> Integer {
+
+    fn read_param(&self, pos: Position, mode: &ParamMode) -> Result<Integer, String> {
         match mode {
-            ParamMode::POSITION => self.get(self.get(pos) as Position),
-            ParamMode::IMMEDIATE => self.get(pos)
+            ParamMode::POSITION => Ok(self.get(Self::address(self.get(pos))?)),
+            ParamMode::IMMEDIATE => Ok(self.get(pos)),
+            ParamMode::RELATIVE => Ok(self.get(Self::address(self.relative_base + self.get(pos))?)),
         }
     }
 
-    fn set(&mut self, pos: Position, val: Integer) {
-        let ptr = &mut self.data[pos as usize];
-        *ptr = val;
-        println!("\t\tSet {} to {}", pos, val);
+    fn read_output_param(&self, pos: Position, mode: &ParamMode) -> Result<Position, String> {
+        match mode {
+            ParamMode::POSITION => Self::address(self.get(pos)),
+            ParamMode::IMMEDIATE => Err("Output param in immediate mode".to_owned()),
+            ParamMode::RELATIVE => Self::address(self.relative_base + self.get(pos)),
+        }
     }
 
-    fn read_input(&mut self) -> Integer {
-        self.input_call_count += 1;
-        let result = (self.input_fn)(self.input_call_count);
-        println!("Read input {}", result);
-        result
-    }
-    fn write_output(&self, val: Integer) {
-        println!("{}", val);
+    fn set(&mut self, pos: Position, val: Integer) -> Result<(), String> {
+        let index = pos as usize;
+        if index >= MAX_MEMORY {
+            return Err(format!("Write to address {} beyond the memory limit of {} cells", pos, MAX_MEMORY));
+        }
+        if index >= self.data.len() {
+            self.data.resize(index + 1, 0);
+        }
+        self.data[index] = val;
+        self.dirty.mark(pos);
+        Ok(())
     }
 
-    pub fn execute(self: &mut Program) -> Option<Integer> {
-        self.input_call_count = 0;
-        let mut pos = 0;
-        let mut prev_output: Option<Integer> = None;
+    /// Run to the end, calling input_fn with the number of the input whenever input is needed,
+    /// and return the last output
+    pub fn execute<F: FnMut(u32) -> Integer>(&mut self, mut input_fn: F) -> Result<Option<Integer>, VmError> {
+        let mut input_call_count = 0;
         loop {
-            let opcode = self.get(pos);
-            let (instruction, modes) = read_opcode(opcode);
-            println!("\tpos: {}, instruction: {:?}, modes: {:?}", pos, instruction, modes);
-            use Instruction::*;
-            match instruction {
-                Add => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.get(pos + 3);
-                    self.set(c as Position, a + b);
-                    pos += 4;
-                }
-                Multiply => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.get(pos + 3) as Position;
-                    self.set(c, a * b);
-                    pos += 4;
+            match self.step()? {
+                Event::NeedsInput => {
+                    input_call_count += 1;
+                    self.inputs.push_back(input_fn(input_call_count));
                 }
-                Input => {
-                    let dest = self.get(pos + 1) as Position;
-                    let input = self.read_input();
-                    self.set(dest, input);
-                    pos += 2;
-                }
-                Output => {
-                    let val = self.read_param(pos + 1, &modes[0]);
-                    self.write_output(val);
-                    prev_output = Some(val);
-                    pos += 2;
-                }
-                JumpIfTrue => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    if a != 0 {
-                        pos = b as Position;
-                    } else {
-                        pos += 3;
-                    }
-                }
-                JumpIfFalse => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    if a == 0 {
-                        pos = b as Position;
-                    } else {
-                        pos += 3;
-                    }
-                }
-                LessThan => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.get(pos + 3);
-                    self.set(c.try_into().unwrap(), (a < b).try_into().unwrap());
-                    pos += 4;
-                }
-                Equals => {
-                    let a = self.read_param(pos + 1, &modes[0]);
-                    let b = self.read_param(pos + 2, &modes[1]);
-                    let c = self.get(pos + 3);
-                    self.set(c.try_into().unwrap(), (a == b).try_into().unwrap());
-                    pos += 4;
+                Event::Halted => break,
+                Event::Stepped | Event::Output => {}
+            }
+        }
+        Ok(self.outputs.last().cloned())
+    }
+
+    pub fn step(&mut self) -> Result<Event, VmError> {
+        if self.halted {
+            return Ok(Event::Halted);
+        }
+        self.try_step().map_err(|reason| self.error(reason))
+    }
+
+    fn try_step(&mut self) -> Result<Event, String> {
+        let pos = self.pos;
+        let opcode = self.get(pos);
+        let (instruction, modes) = read_opcode(opcode)?;
+        use Instruction::*;
+        match instruction {
+            Add => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                let c = self.read_output_param(pos + 3, &modes[2])?;
+                self.set(c, a + b)?;
+                self.pos += 4;
+            }
+            Multiply => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                let c = self.read_output_param(pos + 3, &modes[2])?;
+                self.set(c, a * b)?;
+                self.pos += 4;
+            }
+            Input => {
+                let input = match self.inputs.pop_front() {
+                    Some(input) => input,
+                    None => return Ok(Event::NeedsInput),
+                };
+                let dest = self.read_output_param(pos + 1, &modes[0])?;
+                self.set(dest, input)?;
+                self.pos += 2;
+            }
+            Output => {
+                let val = self.read_param(pos + 1, &modes[0])?;
+                self.outputs.push(val);
+                self.pos += 2;
+                return Ok(Event::Output);
+            }
+            JumpIfTrue => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                if a != 0 {
+                    self.pos = Self::address(b)?;
+                } else {
+                    self.pos += 3;
                 }
-                Halt => {
-                    break;
+            }
+            JumpIfFalse => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                if a == 0 {
+                    self.pos = Self::address(b)?;
+                } else {
+                    self.pos += 3;
                 }
             }
+            LessThan => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                let c = self.read_output_param(pos + 3, &modes[2])?;
+                self.set(c, (a < b).into())?;
+                self.pos += 4;
+            }
+            Equals => {
+                let a = self.read_param(pos + 1, &modes[0])?;
+                let b = self.read_param(pos + 2, &modes[1])?;
+                let c = self.read_output_param(pos + 3, &modes[2])?;
+                self.set(c, (a == b).into())?;
+                self.pos += 4;
+            }
+            RelativeBaseOffset => {
+                self.relative_base += self.read_param(pos + 1, &modes[0])?;
+                self.pos += 2;
+            }
+            Halt => {
+                self.halted = true;
+                return Ok(Event::Halted);
+            }
         }
-        prev_output
+        Ok(Event::Stepped)
     }
 }
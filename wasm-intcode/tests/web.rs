@@ -1,13 +1,258 @@
-//! Test suite for the Web and headless browsers.
+//! Test suite for the exported API, run under Node with `wasm-pack test --node`.
 
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 use wasm_bindgen_test::*;
+use wasm_intcode::*;
 
-wasm_bindgen_test_configure!(run_in_browser);
+const DAY5_COMPARE_TO_8: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+/// Memory after running a program which needs no input
+fn final_memory(code: &str) -> Vec<i64> {
+    let mut machine = Machine::new(code).unwrap();
+    assert_eq!(machine.run_until_event().unwrap(), Event::Halted);
+    machine.memory_view().to_vec()
+}
+
+fn run_with_inputs(code: &str, inputs: &[i64]) -> Vec<i64> {
+    let mut machine = Machine::new(code).unwrap();
+    let mut inputs = inputs.iter();
+    let mut outputs = Vec::new();
+    loop {
+        match machine.run_until_event().unwrap() {
+            Event::NeedsInput => machine.push_input(*inputs.next().expect("Ran out of inputs")),
+            Event::Output => outputs.extend(machine.take_outputs()),
+            Event::Halted => return outputs,
+            Event::Stepped => unreachable!(),
+        }
+    }
+}
+
+fn error_prop(err: &JsValue, name: &str) -> JsValue {
+    js_sys::Reflect::get(err, &name.into()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn day2_examples() {
+    assert_eq!(final_memory("1,9,10,3,2,3,11,0,99,30,40,50")[0], 3500);
+    assert_eq!(final_memory("1,0,0,0,99"), vec![2, 0, 0, 0, 99]);
+    assert_eq!(final_memory("2,3,0,3,99"), vec![2, 3, 0, 6, 99]);
+    assert_eq!(final_memory("2,4,4,5,99,0"), vec![2, 4, 4, 5, 99, 9801]);
+    assert_eq!(final_memory("1,1,1,4,99,5,6,0,99"), vec![30, 1, 1, 4, 2, 5, 6, 0, 99]);
+}
+
+#[wasm_bindgen_test]
+fn day5_param_modes() {
+    assert_eq!(final_memory("1002,4,3,4,33"), vec![1002, 4, 3, 4, 99]);
+    assert_eq!(final_memory("1101,100,-1,4,0"), vec![1101, 100, -1, 4, 99]);
+}
+
+#[wasm_bindgen_test]
+fn day5_comparisons() {
+    assert_eq!(run_with_inputs("3,9,8,9,10,9,4,9,99,-1,8", &[8]), vec![1]);
+    assert_eq!(run_with_inputs("3,9,8,9,10,9,4,9,99,-1,8", &[7]), vec![0]);
+    assert_eq!(run_with_inputs("3,9,7,9,10,9,4,9,99,-1,8", &[7]), vec![1]);
+    assert_eq!(run_with_inputs("3,3,1108,-1,8,3,4,3,99", &[8]), vec![1]);
+    assert_eq!(run_with_inputs("3,3,1107,-1,8,3,4,3,99", &[9]), vec![0]);
+}
+
+#[wasm_bindgen_test]
+fn day5_jumps() {
+    assert_eq!(run_with_inputs("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", &[0]), vec![0]);
+    assert_eq!(run_with_inputs("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", &[5]), vec![1]);
+    assert_eq!(run_with_inputs(DAY5_COMPARE_TO_8, &[7]), vec![999]);
+    assert_eq!(run_with_inputs(DAY5_COMPARE_TO_8, &[8]), vec![1000]);
+    assert_eq!(run_with_inputs(DAY5_COMPARE_TO_8, &[9]), vec![1001]);
+}
+
+#[wasm_bindgen_test]
+fn run_program_uses_1_as_input() {
+    assert_eq!(run_program("3,9,8,9,10,9,4,9,99,-1,1").unwrap(), 1);
+    assert_eq!(run_program(DAY5_COMPARE_TO_8).unwrap(), 999);
+}
+
+#[wasm_bindgen_test]
+fn run_program_with_input_callback() {
+    let input = js_sys::Function::new_with_args("n", "return 9");
+    assert_eq!(run_program_with_input(DAY5_COMPARE_TO_8, &input).unwrap(), Some(1001));
+
+    let input_numbers = Rc::new(RefCell::new(Vec::new()));
+    let recorded = input_numbers.clone();
+    let input = Closure::wrap(Box::new(move |n: u32| {
+        recorded.borrow_mut().push(n);
+        n * 10
+    }) as Box<dyn FnMut(u32) -> u32>);
+    let output = js_sys::Function::new_no_args("");
+    let sum = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+    let result = run_program_with_io(sum, input.as_ref().unchecked_ref(), &output).unwrap();
+    assert_eq!(result, Some(30));
+    assert_eq!(*input_numbers.borrow(), vec![1, 2]);
+}
+
+#[wasm_bindgen_test]
+fn run_program_with_output_callback() {
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let recorded = outputs.clone();
+    let output = Closure::wrap(Box::new(move |val: i64| {
+        recorded.borrow_mut().push(val);
+    }) as Box<dyn FnMut(i64)>);
+    let input = js_sys::Function::new_no_args("return 0");
+    let result = run_program_with_io("104,1,104,2,104,3,99", &input, output.as_ref().unchecked_ref()).unwrap();
+    assert_eq!(result, Some(3));
+    assert_eq!(*outputs.borrow(), vec![1, 2, 3]);
+}
+
+#[wasm_bindgen_test]
+fn machine_steps() {
+    let mut machine = Machine::new("1101,2,3,9,4,9,99,0,0,0").unwrap();
+    assert_eq!(machine.pc(), 0);
+    assert_eq!(machine.step().unwrap(), Event::Stepped);
+    assert_eq!(machine.pc(), 4);
+    assert_eq!(machine.step().unwrap(), Event::Output);
+    assert_eq!(machine.take_outputs(), vec![5]);
+    assert_eq!(machine.take_outputs(), Vec::<i64>::new());
+    assert_eq!(machine.step().unwrap(), Event::Halted);
+    assert_eq!(machine.step().unwrap(), Event::Halted);
+    assert_eq!(machine.relative_base(), 0);
+}
+
+#[wasm_bindgen_test]
+fn machine_waits_for_input() {
+    let mut machine = Machine::new("3,5,4,5,99,0").unwrap();
+    assert_eq!(machine.run_until_event().unwrap(), Event::NeedsInput);
+    assert_eq!(machine.run_until_event().unwrap(), Event::NeedsInput);
+    assert_eq!(machine.pc(), 0);
+    machine.push_input(42);
+    assert_eq!(machine.run_until_event().unwrap(), Event::Output);
+    assert_eq!(machine.take_outputs(), vec![42]);
+}
+
+#[wasm_bindgen_test]
+fn memory_view_and_dirty_ranges() {
+    let mut machine = Machine::new("1101,1,1,13,1101,1,1,15,1101,1,1,14,99,0,0,0").unwrap();
+    assert_eq!(machine.memory_len(), 16);
+    assert_eq!(machine.run_until_event().unwrap(), Event::Halted);
+    let view = machine.memory_view();
+    assert_eq!(view.length(), 16);
+    assert_eq!(view.get_index(13), 2);
+    assert_eq!(machine.take_dirty_ranges(), vec![13, 16]);
+    assert_eq!(machine.take_dirty_ranges(), Vec::<u32>::new());
+}
+
+#[wasm_bindgen_test]
+fn unknown_opcode_throws_with_pc_and_opcode() {
+    let err = run_program("1101,1,1,5,77,0,99").unwrap_err();
+    assert_eq!(error_prop(&err, "pc"), 4);
+    assert_eq!(error_prop(&err, "opcode"), 77i64);
+    let message = err.dyn_into::<js_sys::Error>().unwrap().message();
+    assert!(String::from(message).contains("at pc 4"));
+}
+
+#[wasm_bindgen_test]
+fn invalid_param_mode_throws() {
+    let mut machine = Machine::new("30001,0,0,0,99").unwrap();
+    let err = machine.step().unwrap_err();
+    assert_eq!(error_prop(&err, "pc"), 0);
+    assert_eq!(error_prop(&err, "opcode"), 30001i64);
+}
+
+#[wasm_bindgen_test]
+fn memory_grows_when_written_past_the_end() {
+    let mut machine = Machine::new("1101,2,3,10,4,10,4,1000,99").unwrap();
+    assert_eq!(machine.memory_len(), 9);
+    assert_eq!(machine.run_until_event().unwrap(), Event::Output);
+    assert_eq!(machine.memory_len(), 11);
+    assert_eq!(machine.memory_view().get_index(10), 5);
+    assert_eq!(machine.take_dirty_ranges(), vec![10, 11]);
+//    Reading past the end gives 0 without growing
+    assert_eq!(machine.run_until_event().unwrap(), Event::Output);
+    assert_eq!(machine.take_outputs(), vec![5, 0]);
+    assert_eq!(machine.memory_len(), 11);
+}
+
+#[wasm_bindgen_test]
+fn invalid_address_throws() {
+    let err = run_program("1,0,0,1000000000,99").unwrap_err();
+    assert_eq!(error_prop(&err, "pc"), 0);
+    let err = run_program("4,-1,99").unwrap_err();
+    assert_eq!(error_prop(&err, "opcode"), 4i64);
+    let err = run_program("109,-5,204,0,99").unwrap_err();
+    assert_eq!(error_prop(&err, "pc"), 2);
+}
+
+#[wasm_bindgen_test]
+fn immediate_output_param_throws() {
+    let err = run_program("11101,1,1,0,99").unwrap_err();
+    assert_eq!(error_prop(&err, "opcode"), 11101i64);
+}
+
+#[wasm_bindgen_test]
+fn non_numeric_input_throws() {
+    let input = js_sys::Function::new_no_args("return 'eight'");
+    let err = run_program_with_input("3,0,99", &input).unwrap_err();
+    assert_eq!(error_prop(&err, "pc"), 0);
+    assert_eq!(error_prop(&err, "opcode"), 3i64);
+}
+
+#[wasm_bindgen_test]
+fn exception_from_input_callback_propagates() {
+    let input = js_sys::Function::new_no_args("throw new Error('no input')");
+    let err = run_program_with_input("3,0,99", &input).unwrap_err();
+    let message = err.dyn_into::<js_sys::Error>().unwrap().message();
+    assert_eq!(String::from(message), "no input");
+}
+
+#[wasm_bindgen_test]
+fn invalid_program_text_throws() {
+    assert!(Machine::new("1,0,x,99").is_err());
+    assert!(run_program("").is_err());
+}
+
+#[wasm_bindgen_test]
+fn program_without_output_throws() {
+    assert!(run_program("1,0,0,0,99").is_err());
+}
 
 #[wasm_bindgen_test]
-fn pass() {
-    assert_eq!(1 + 1, 2);
+fn day9_examples() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let expected: Vec<i64> = quine.split(',').map(|x| x.parse().unwrap()).collect();
+    assert_eq!(run_with_inputs(quine, &[]), expected);
+    let sixteen_digits = run_with_inputs("1102,34915192,34915192,7,4,7,99,0", &[]);
+    assert_eq!(sixteen_digits[0].to_string().len(), 16);
+    assert_eq!(run_with_inputs("104,1125899906842624,99", &[]), vec![1125899906842624]);
+}
+
+#[wasm_bindgen_test]
+fn relative_mode() {
+    let mut machine = Machine::new("109,10,203,0,21201,0,1,1,204,1,99").unwrap();
+    assert_eq!(machine.run_until_event().unwrap(), Event::NeedsInput);
+    assert_eq!(machine.relative_base(), 10);
+    machine.push_input(41);
+    assert_eq!(machine.run_until_event().unwrap(), Event::Output);
+    assert_eq!(machine.take_outputs(), vec![42]);
+    assert_eq!(machine.memory_view().to_vec()[10..], [41, 42]);
+}
+
+#[wasm_bindgen_test]
+fn values_cross_to_js_as_bigint() {
+    let big = 1i64 << 60;
+    assert_eq!(JsValue::from(run_program(&format!("104,{},99", big)).unwrap()), JsValue::from(big));
+
+    let input = js_sys::Function::new_no_args("return 2n ** 60n");
+    let outputs = js_sys::Array::new();
+    let output = js_sys::Function::new_with_args("val", "this.push(val)");
+    let output = output.bind0(&outputs);
+    let result = run_program_with_io("3,0,4,0,99", &input, &output).unwrap();
+    assert_eq!(result, Some(big));
+    assert!(outputs.get(0).is_bigint());
+    assert_eq!(outputs.get(0), big);
+
+    let input = js_sys::Function::new_no_args("return 0.5");
+    assert!(run_program_with_input("3,0,99", &input).is_err());
 }